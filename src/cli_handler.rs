@@ -0,0 +1,243 @@
+pub mod cli_handler {
+    use crate::config_handler::config_handler::Config;
+    use crate::data_handler::data_handler::{
+        back_up, export_todo_txt, import_todo_txt, list_backups, list_names, load_backup, to_todo_txt,
+    };
+    use crate::output_handler::output_handler::{write_items, OutputFormat};
+    use crate::parsing_handler::{handle_command, parse_args};
+    use crate::todo_backend::todo::{
+        Archive, Filter, History, Shelf, TodoError, TodoItem, TodoList, TodoResult,
+    };
+    use crate::tui_handler::tui_handler::State;
+    use std::io;
+    use std::path::Path;
+
+    const HELP: &str = "\
+Usage: todo [--list name | --file path] [command] [arguments]
+
+Without a command the TUI is opened.
+
+Options:
+  --list <name>               works on a named list, ex: work, lists are created when first used
+  --file <path>               works on the given file, TODO_FILE does the same unless --file or --list is given
+
+Commands:
+  add <title> [--due date] [--prio priority] [--every rule] [--project name] [--tags a,b] [--parent id]
+  sub <parent id> <title> [options like add]
+  list [+project] [@tag] [--completed | --all] [--format plain | json | csv | tsv]
+  done <ids>                  ids can be lists and ranges, ex: 4 7-9
+  undone <ids>
+  edit <ids> [--title title] [--due date] [--prio priority] [--every rule] [--project name] [--tags a,b]
+  rm <ids | completed | todo | +project | @tag | older:days>
+  archive [days]              moves tasks completed more than days (default 30) ago into the archive
+  archived [search]           lists the archive, takes --format like list
+  restore <ids>               restores archived tasks
+  restore --backup [number]   lists the backups, or restores the list and archive from one
+  trash [search]              lists the trash, takes --format like list
+  undelete <ids>              restores deleted tasks
+  emptytrash
+  undo [times], redo [times]
+  import <file>               adds the tasks in a todo.txt file, lines that can't be read are listed
+  export [file]               writes the list as todo.txt, without a file it is printed
+  lists                       prints the named lists
+  keys                        prints the key bindings of the TUI
+  help
+
+Every command of the TUI command bar works here too, options can be given as --key value or key:value.";
+
+    ///Runs a single command given on the command line, ex: todo add "Write report" --due tomorrow.
+    ///Changes go through the undo history like they do in the TUI
+    pub fn run_cli(
+        args: &[String],
+        file: &Path,
+        todo: &mut TodoList,
+        archive: &mut Archive,
+        history: &mut History,
+        config: &Config,
+    ) -> TodoResult<()> {
+        let rest = &args[1..];
+        match args[0].as_str() {
+            "help" | "--help" | "-h" => {
+                println!("{HELP}");
+                return Ok(());
+            }
+            "list" | "ls" => return list(rest, todo),
+            "archived" => return list_shelf(rest, archive, Shelf::Archive),
+            "trash" => return list_shelf(rest, archive, Shelf::Trash),
+            "restore" if rest.iter().any(|arg| arg.starts_with("--backup")) => {
+                return restore_backup(rest, file, todo, archive, history, config.backups);
+            }
+            "keys" | "ShowKeys" => {
+                for (action, keys) in config.keys.bindings() {
+                    println!("{:<16} {:<16} {}", keys.join(", "), action.name(), action.description());
+                }
+                return Ok(());
+            }
+            "import" => return import(rest, todo, archive, history),
+            "export" => {
+                match rest {
+                    [] => print!("{}", to_todo_txt(todo)),
+                    [path] => export_todo_txt(todo, Path::new(path))?,
+                    _ => return Err(TodoError::invalid_input(&rest.join(" "), "Expected export [file]")),
+                }
+                return Ok(());
+            }
+            "lists" => {
+                list_names()?.iter().for_each(|name| println!("{name}"));
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        let command = parse_args(args)?;
+        let next_id = todo.next_id;
+        history.begin(todo, archive);
+        let result = run_command(command, &args.join(" "), todo, archive, history);
+        history.commit(todo, archive);
+        result?;
+
+        if matches!(args[0].as_str(), "add" | "sub") && todo.next_id > next_id {
+            println!("Added {}", todo.next_id - 1);
+        }
+        return Ok(());
+    }
+
+    fn run_command(
+        command: crate::parsing_handler::Command,
+        input: &str,
+        todo: &mut TodoList,
+        archive: &mut Archive,
+        history: &mut History,
+    ) -> TodoResult<()> {
+        let end = input.chars().count();
+        match handle_command(command, todo, archive, &mut Filter::default(), history)? {
+            State::Viewing | State::ViewingArchive(_) | State::Quitting => {}
+            //there is nobody to ask, the trash is there to undo mistakes
+            State::ConfirmingDelete(item_ids) => {
+                let deleted = todo.delete_items(&item_ids, archive);
+                println!("Deleted {deleted} item(s)");
+            }
+            State::EditingTodo(_) => {
+                return Err(TodoError::parse(input, end, "a field to change, ex: --title"));
+            }
+            //states that would ask for the rest in the TUI
+            _ => return Err(TodoError::parse(input, end, "more arguments")),
+        }
+        return Ok(());
+    }
+
+    ///Without a number the backups are listed newest first, with one that backup replaces the list
+    ///and the archive. The list being replaced is backed up first however recent the newest backup is,
+    ///so this can be undone by restoring again
+    fn restore_backup(
+        args: &[String],
+        file: &Path,
+        todo: &mut TodoList,
+        archive: &mut Archive,
+        history: &mut History,
+        keep: usize,
+    ) -> TodoResult<()> {
+        let backups = list_backups(file)?;
+        let number = match args {
+            [flag] if flag == "--backup" => None,
+            [flag, number] if flag == "--backup" => Some(number.as_str()),
+            [flag] => flag.strip_prefix("--backup="),
+            _ => return Err(TodoError::invalid_input(&args.join(" "), "Expected restore --backup [number]")),
+        };
+        let number = match number {
+            Some(number) => number,
+            None => {
+                if backups.is_empty() {
+                    println!("There are no backups of {}", file.display());
+                }
+                for (index, backup) in backups.iter().enumerate() {
+                    println!("{:>3}  {}", index + 1, backup.name);
+                }
+                return Ok(());
+            }
+        };
+        let backup = match number.parse::<usize>().ok().and_then(|n| n.checked_sub(1)) {
+            Some(index) if index < backups.len() => &backups[index],
+            _ => return Err(TodoError::invalid_input(number, "Expected the number of a backup from restore --backup")),
+        };
+
+        let (restored_todo, restored_archive) = load_backup(backup)?;
+        back_up(file, &restored_todo, &restored_archive, keep)?;
+        history.begin(todo, archive);
+        *todo = restored_todo;
+        *archive = restored_archive;
+        history.commit(todo, archive);
+        println!("Restored the backup from {}", backup.name);
+        return Ok(());
+    }
+
+    ///Adds the tasks of a todo.txt file in one change, so undo takes them all out again
+    fn import(args: &[String], todo: &mut TodoList, archive: &mut Archive, history: &mut History) -> TodoResult<()> {
+        let path = match args {
+            [path] => Path::new(path),
+            _ => return Err(TodoError::invalid_input(&args.join(" "), "Expected import <file>")),
+        };
+        let (items, skipped) = import_todo_txt(path)?;
+        history.begin(todo, archive);
+        let added = todo.add_imported(items);
+        history.commit(todo, archive);
+
+        println!("Imported {added} item(s) from {}", path.display());
+        for line in &skipped {
+            println!("Skipped line {}, {}: {}", line.number, line.reason, line.line);
+        }
+        return Ok(());
+    }
+
+    ///Prints the todo items, --completed prints the completed ones instead and --all prints both.
+    ///+project and @tag words filter the list like in the TUI
+    fn list(args: &[String], todo: &TodoList) -> TodoResult<()> {
+        let (format, words) = output_options(args)?;
+        let (mut show_todo, mut show_completed) = (true, false);
+        let mut filter_words = Vec::new();
+        for word in words {
+            match word {
+                "--completed" => (show_todo, show_completed) = (false, true),
+                "--all" => (show_todo, show_completed) = (true, true),
+                word => filter_words.push(word),
+            }
+        }
+        let filter = filter_words.join(" ").parse::<Filter>()?;
+
+        let mut items: Vec<&TodoItem> = Vec::new();
+        if show_todo {
+            items.extend(todo.todo_items.iter());
+        }
+        if show_completed {
+            items.extend(todo.completed_items.iter());
+        }
+        let items = items.into_iter().filter(|item| filter.matches(item));
+        return write_items(&mut io::stdout().lock(), items, format);
+    }
+
+    fn list_shelf(args: &[String], archive: &Archive, shelf: Shelf) -> TodoResult<()> {
+        let (format, words) = output_options(args)?;
+        let items = archive.search(shelf, &words.join(" "));
+        return write_items(&mut io::stdout().lock(), items, format);
+    }
+
+    ///Takes --format out of the arguments, ex: --format csv or --format=json
+    fn output_options(args: &[String]) -> TodoResult<(OutputFormat, Vec<&str>)> {
+        let mut format = OutputFormat::default();
+        let mut words = Vec::new();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.split_once('=') {
+                Some(("--format", value)) => format = value.parse()?,
+                _ if arg == "--format" => match args.next() {
+                    Some(value) => format = value.parse()?,
+                    None => {
+                        return Err(TodoError::invalid_input(arg, "Expected plain, json, csv or tsv"))
+                    }
+                },
+                _ => words.push(arg.as_str()),
+            }
+        }
+        return Ok((format, words));
+    }
+}
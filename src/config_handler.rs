@@ -0,0 +1,550 @@
+pub mod theme;
+
+pub mod config_handler {
+    pub use super::theme::{Theme, ThemeSpec, DEFAULT_THEME, NO_COLOR_THEME};
+    use crate::data_handler::data_handler::DEFAULT_BACKUPS;
+    use crate::todo_backend::todo::{TodoError, TodoResult, DEFAULT_ARCHIVE_DAYS, DEFAULT_UNDO_DEPTH, MAX_ARCHIVE_DAYS};
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use home::home_dir;
+    use serde::Deserialize;
+    use std::collections::HashMap;
+    use std::{env, fmt, fs, io, path::Path, path::PathBuf, str::FromStr};
+
+    ///Settings read from config.toml, anything left out keeps its default
+    pub struct Config {
+        pub keys: KeyMap,
+        pub undo_depth: usize,
+        pub persist_undo: bool,
+        //completed items older than this are archived when the app starts
+        pub archive_days: i64,
+        //how many backups of each list are kept, 0 turns them off
+        pub backups: usize,
+        pub theme: Theme,
+    }
+    impl Default for Config {
+        fn default() -> Config {
+            Config {
+                keys: KeyMap::default(),
+                undo_depth: DEFAULT_UNDO_DEPTH,
+                persist_undo: false,
+                archive_days: DEFAULT_ARCHIVE_DAYS,
+                backups: DEFAULT_BACKUPS,
+                theme: Theme::default(),
+            }
+        }
+    }
+
+    ///The file as written, ex:
+    ///undo_depth = 50
+    ///[keys]
+    ///complete = ["c", "space"]
+    ///redo = "ctrl+r"
+    #[derive(Deserialize, Default)]
+    #[serde(default, deny_unknown_fields)]
+    struct ConfigFile {
+        undo_depth: Option<usize>,
+        persist_undo: Option<bool>,
+        archive_days: Option<i64>,
+        backups: Option<usize>,
+        theme: Option<String>,
+        keys: HashMap<String, KeyList>,
+        themes: HashMap<String, ThemeSpec>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum KeyList {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    ///$XDG_CONFIG_HOME/todo-list/config.toml or ~/.config/todo-list/config.toml
+    pub fn config_path() -> PathBuf {
+        let base = match env::var_os("XDG_CONFIG_HOME").map(PathBuf::from) {
+            Some(r) if r.is_absolute() => r,
+            _ => home_dir().expect("Could not find home directory").join(".config"),
+        };
+        return base.join("todo-list").join("config.toml");
+    }
+
+    ///A missing file gives the default config, anything invalid in it is an error.
+    ///Without a theme in the file NO_COLOR picks the monochrome theme
+    pub fn load_config(file: &Path) -> TodoResult<Config> {
+        let contents = match fs::read_to_string(file) {
+            Ok(r) => r,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let invalid = |reason: String| TodoError::Config { file: file.display().to_string(), reason };
+        let config_file: ConfigFile = toml::from_str(&contents).map_err(|e| invalid(e.to_string()))?;
+
+        let mut config = Config::default();
+        config.undo_depth = config_file.undo_depth.unwrap_or(config.undo_depth);
+        config.persist_undo = config_file.persist_undo.unwrap_or(config.persist_undo);
+        config.archive_days = match config_file.archive_days {
+            Some(days) if !(0..=MAX_ARCHIVE_DAYS).contains(&days) => {
+                return Err(invalid(format!("archive_days should be from 0 to {MAX_ARCHIVE_DAYS}, got {days}")));
+            }
+            days => days.unwrap_or(config.archive_days),
+        };
+        config.backups = config_file.backups.unwrap_or(config.backups);
+        config.keys = KeyMap::with_overrides(config_file.keys).map_err(invalid)?;
+        let no_color = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        let theme = match (&config_file.theme, no_color) {
+            (Some(name), _) => name,
+            (None, true) => NO_COLOR_THEME,
+            (None, false) => DEFAULT_THEME,
+        };
+        config.theme = Theme::find(theme, &config_file.themes).map_err(invalid)?;
+        return Ok(config);
+    }
+
+    ///Everything a key can be bound to in the list view
+    #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+    pub enum KeyAction {
+        Command,
+        Quit,
+        Add,
+        AddDate,
+        Complete,
+        CompleteAll,
+        Uncomplete,
+        Priority,
+        Repeat,
+        Filter,
+        Subtask,
+        Indent,
+        Outdent,
+        Toggle,
+        Archive,
+        Trash,
+        Undo,
+        Redo,
+        Delete,
+        Edit,
+        Lists,
+        Keys,
+        PriorityNone,
+        PriorityLow,
+        PriorityMedium,
+        PriorityHigh,
+        PriorityUrgent,
+        Up,
+        Down,
+        PageUp,
+        PageDown,
+        Top,
+        Bottom,
+        NextSection,
+    }
+    impl KeyAction {
+        //in the order the keys view lists them
+        pub const ALL: [KeyAction; 34] = [
+            KeyAction::Command,
+            KeyAction::Quit,
+            KeyAction::Add,
+            KeyAction::AddDate,
+            KeyAction::Complete,
+            KeyAction::CompleteAll,
+            KeyAction::Uncomplete,
+            KeyAction::Priority,
+            KeyAction::Repeat,
+            KeyAction::Filter,
+            KeyAction::Subtask,
+            KeyAction::Indent,
+            KeyAction::Outdent,
+            KeyAction::Toggle,
+            KeyAction::Archive,
+            KeyAction::Trash,
+            KeyAction::Undo,
+            KeyAction::Redo,
+            KeyAction::Delete,
+            KeyAction::Edit,
+            KeyAction::Lists,
+            KeyAction::Keys,
+            KeyAction::PriorityNone,
+            KeyAction::PriorityLow,
+            KeyAction::PriorityMedium,
+            KeyAction::PriorityHigh,
+            KeyAction::PriorityUrgent,
+            KeyAction::Up,
+            KeyAction::Down,
+            KeyAction::PageUp,
+            KeyAction::PageDown,
+            KeyAction::Top,
+            KeyAction::Bottom,
+            KeyAction::NextSection,
+        ];
+
+        ///The name used in the [keys] table
+        pub fn name(&self) -> &'static str {
+            return match self {
+                KeyAction::Command => "command",
+                KeyAction::Quit => "quit",
+                KeyAction::Add => "add",
+                KeyAction::AddDate => "add_date",
+                KeyAction::Complete => "complete",
+                KeyAction::CompleteAll => "complete_all",
+                KeyAction::Uncomplete => "uncomplete",
+                KeyAction::Priority => "priority",
+                KeyAction::Repeat => "repeat",
+                KeyAction::Filter => "filter",
+                KeyAction::Subtask => "subtask",
+                KeyAction::Indent => "indent",
+                KeyAction::Outdent => "outdent",
+                KeyAction::Toggle => "toggle",
+                KeyAction::Archive => "archive",
+                KeyAction::Trash => "trash",
+                KeyAction::Undo => "undo",
+                KeyAction::Redo => "redo",
+                KeyAction::Delete => "delete",
+                KeyAction::Edit => "edit",
+                KeyAction::Lists => "lists",
+                KeyAction::Keys => "keys",
+                KeyAction::PriorityNone => "priority_none",
+                KeyAction::PriorityLow => "priority_low",
+                KeyAction::PriorityMedium => "priority_medium",
+                KeyAction::PriorityHigh => "priority_high",
+                KeyAction::PriorityUrgent => "priority_urgent",
+                KeyAction::Up => "up",
+                KeyAction::Down => "down",
+                KeyAction::PageUp => "page_up",
+                KeyAction::PageDown => "page_down",
+                KeyAction::Top => "top",
+                KeyAction::Bottom => "bottom",
+                KeyAction::NextSection => "next_section",
+            };
+        }
+
+        pub fn description(&self) -> &'static str {
+            return match self {
+                KeyAction::Command => "Enter a command",
+                KeyAction::Quit => "Quit",
+                KeyAction::Add => "Add a task",
+                KeyAction::AddDate => "Add a task with a due date",
+                KeyAction::Complete => "Complete the selected task",
+                KeyAction::CompleteAll => "Complete a task with its subtasks",
+                KeyAction::Uncomplete => "Uncomplete the selected task",
+                KeyAction::Priority => "Set the priority of a task",
+                KeyAction::Repeat => "Make a task repeat",
+                KeyAction::Filter => "Filter by project and tags",
+                KeyAction::Subtask => "Add a subtask",
+                KeyAction::Indent => "Indent a task under the one above",
+                KeyAction::Outdent => "Outdent a subtask",
+                KeyAction::Toggle => "Collapse or expand subtasks",
+                KeyAction::Archive => "Open the archive",
+                KeyAction::Trash => "Open the trash",
+                KeyAction::Undo => "Undo the last change",
+                KeyAction::Redo => "Redo the last undone change",
+                KeyAction::Delete => "Delete the selected task",
+                KeyAction::Edit => "Edit the selected task",
+                KeyAction::Lists => "Open the list picker",
+                KeyAction::Keys => "Show the key bindings",
+                KeyAction::PriorityNone => "Set the selected task's priority to none",
+                KeyAction::PriorityLow => "Set the selected task's priority to low",
+                KeyAction::PriorityMedium => "Set the selected task's priority to medium",
+                KeyAction::PriorityHigh => "Set the selected task's priority to high",
+                KeyAction::PriorityUrgent => "Set the selected task's priority to urgent",
+                KeyAction::Up => "Move the selection up",
+                KeyAction::Down => "Move the selection down",
+                KeyAction::PageUp => "Move the selection up a page",
+                KeyAction::PageDown => "Move the selection down a page",
+                KeyAction::Top => "Select the first item",
+                KeyAction::Bottom => "Select the last item",
+                KeyAction::NextSection => "Move between todo and completed",
+            };
+        }
+
+        fn default_keys(&self) -> &'static [&'static str] {
+            return match self {
+                KeyAction::Command => &[":"],
+                KeyAction::Quit => &["q"],
+                KeyAction::Add => &["n"],
+                KeyAction::AddDate => &["d"],
+                KeyAction::Complete => &["c"],
+                KeyAction::CompleteAll => &["C"],
+                KeyAction::Uncomplete => &["u"],
+                KeyAction::Priority => &["p"],
+                KeyAction::Repeat => &["r"],
+                KeyAction::Filter => &["f"],
+                KeyAction::Subtask => &["s"],
+                KeyAction::Indent => &[">"],
+                KeyAction::Outdent => &["<"],
+                KeyAction::Toggle => &["z"],
+                KeyAction::Archive => &["a"],
+                KeyAction::Trash => &["t"],
+                KeyAction::Undo => &["U"],
+                KeyAction::Redo => &["ctrl+r"],
+                KeyAction::Delete => &["x"],
+                KeyAction::Edit => &["e"],
+                KeyAction::Lists => &["L"],
+                KeyAction::Keys => &["?"],
+                KeyAction::PriorityNone => &["0"],
+                KeyAction::PriorityLow => &["1"],
+                KeyAction::PriorityMedium => &["2"],
+                KeyAction::PriorityHigh => &["3"],
+                KeyAction::PriorityUrgent => &["4"],
+                KeyAction::Up => &["up", "k"],
+                KeyAction::Down => &["down", "j"],
+                KeyAction::PageUp => &["pgup"],
+                KeyAction::PageDown => &["pgdn"],
+                KeyAction::Top => &["home"],
+                KeyAction::Bottom => &["end"],
+                KeyAction::NextSection => &["tab"],
+            };
+        }
+    }
+
+    ///A key together with its modifiers, ex: ctrl+r, alt+enter, shift+tab or C
+    #[derive(Copy, Clone, PartialEq, Eq, Hash)]
+    pub struct KeyBinding {
+        code: KeyCode,
+        modifiers: KeyModifiers,
+    }
+    impl KeyBinding {
+        //shift is part of the character (C instead of c), so it is left out of character bindings
+        fn new(code: KeyCode, mut modifiers: KeyModifiers) -> KeyBinding {
+            if let KeyCode::Char(_) | KeyCode::BackTab = code {
+                modifiers.remove(KeyModifiers::SHIFT);
+            }
+            return KeyBinding { code, modifiers };
+        }
+    }
+    impl From<KeyEvent> for KeyBinding {
+        fn from(event: KeyEvent) -> KeyBinding {
+            return KeyBinding::new(event.code, event.modifiers);
+        }
+    }
+
+    impl FromStr for KeyBinding {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<KeyBinding, String> {
+            //a lone + is the key itself, ex: "+" or "ctrl++"
+            let (modifier_names, key) = match s.strip_suffix("++") {
+                Some(rest) => (rest, "+"),
+                None => match s.rsplit_once('+') {
+                    Some((rest, key)) if !key.is_empty() => (rest, key),
+                    _ => ("", s),
+                },
+            };
+            let mut modifiers = KeyModifiers::NONE;
+            for name in modifier_names.split('+').filter(|name| !name.is_empty()) {
+                modifiers |= match &*name.to_lowercase() {
+                    "ctrl" | "control" => KeyModifiers::CONTROL,
+                    "alt" => KeyModifiers::ALT,
+                    "shift" => KeyModifiers::SHIFT,
+                    _ => return Err(format!("Unknown modifier '{name}' in '{s}', expected ctrl, alt or shift")),
+                };
+            }
+
+            let mut chars = key.chars();
+            let code = match (chars.next(), chars.next()) {
+                (Some(c), None) if modifiers.contains(KeyModifiers::SHIFT) => {
+                    KeyCode::Char(c.to_ascii_uppercase())
+                }
+                (Some(c), None) => KeyCode::Char(c),
+                _ => match &*key.to_lowercase() {
+                    "space" => KeyCode::Char(' '),
+                    "enter" | "return" => KeyCode::Enter,
+                    "esc" | "escape" => KeyCode::Esc,
+                    "tab" if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+                    "tab" => KeyCode::Tab,
+                    "backtab" => KeyCode::BackTab,
+                    "backspace" => KeyCode::Backspace,
+                    "delete" | "del" => KeyCode::Delete,
+                    "insert" | "ins" => KeyCode::Insert,
+                    "up" => KeyCode::Up,
+                    "down" => KeyCode::Down,
+                    "left" => KeyCode::Left,
+                    "right" => KeyCode::Right,
+                    "home" => KeyCode::Home,
+                    "end" => KeyCode::End,
+                    "pgup" | "pageup" => KeyCode::PageUp,
+                    "pgdn" | "pagedown" => KeyCode::PageDown,
+                    name => match name.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                        Some(n) if (1..=12).contains(&n) => KeyCode::F(n),
+                        _ => return Err(format!("Unknown key '{key}' in '{s}'")),
+                    },
+                },
+            };
+            return Ok(KeyBinding::new(code, modifiers));
+        }
+    }
+
+    impl fmt::Display for KeyBinding {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            if self.modifiers.contains(KeyModifiers::CONTROL) {
+                write!(f, "ctrl+")?;
+            }
+            if self.modifiers.contains(KeyModifiers::ALT) {
+                write!(f, "alt+")?;
+            }
+            if self.modifiers.contains(KeyModifiers::SHIFT) {
+                write!(f, "shift+")?;
+            }
+            match self.code {
+                KeyCode::Char(' ') => write!(f, "space"),
+                KeyCode::Char(c) => write!(f, "{c}"),
+                KeyCode::Enter => write!(f, "enter"),
+                KeyCode::Esc => write!(f, "esc"),
+                KeyCode::Tab => write!(f, "tab"),
+                KeyCode::BackTab => write!(f, "shift+tab"),
+                KeyCode::Backspace => write!(f, "backspace"),
+                KeyCode::Delete => write!(f, "delete"),
+                KeyCode::Insert => write!(f, "insert"),
+                KeyCode::Up => write!(f, "up"),
+                KeyCode::Down => write!(f, "down"),
+                KeyCode::Left => write!(f, "left"),
+                KeyCode::Right => write!(f, "right"),
+                KeyCode::Home => write!(f, "home"),
+                KeyCode::End => write!(f, "end"),
+                KeyCode::PageUp => write!(f, "pgup"),
+                KeyCode::PageDown => write!(f, "pgdn"),
+                KeyCode::F(n) => write!(f, "f{n}"),
+                KeyCode::Null => write!(f, "null"),
+            }
+        }
+    }
+
+    ///Which action each key does in the list view, every key has at most one action
+    pub struct KeyMap {
+        actions: HashMap<KeyBinding, KeyAction>,
+    }
+    impl Default for KeyMap {
+        fn default() -> KeyMap {
+            return KeyMap::with_overrides(HashMap::new()).expect("The default keys conflict");
+        }
+    }
+    impl KeyMap {
+        ///Actions in overrides get the given keys instead of their default ones,
+        ///a key bound to two actions is an error naming both
+        fn with_overrides(mut overrides: HashMap<String, KeyList>) -> Result<KeyMap, String> {
+            let mut actions = HashMap::new();
+            for action in KeyAction::ALL {
+                let keys = match overrides.remove(action.name()) {
+                    Some(KeyList::One(key)) => vec![key],
+                    Some(KeyList::Many(keys)) => keys,
+                    None => action.default_keys().iter().map(|key| key.to_string()).collect(),
+                };
+                for key in keys {
+                    let binding = key.parse::<KeyBinding>()?;
+                    if let Some(other) = actions.insert(binding, action) {
+                        if other != action {
+                            return Err(format!(
+                                "'{binding}' is bound to both {} and {}",
+                                other.name(),
+                                action.name()
+                            ));
+                        }
+                    }
+                }
+            }
+            if let Some(name) = overrides.keys().next() {
+                return Err(format!("Unknown action '{name}' in [keys]"));
+            }
+            return Ok(KeyMap { actions });
+        }
+
+        pub fn action(&self, event: KeyEvent) -> Option<KeyAction> {
+            return self.actions.get(&KeyBinding::from(event)).copied();
+        }
+
+        ///Every action with the keys bound to it, ex: (Complete, ["c", "space"])
+        pub fn bindings(&self) -> Vec<(KeyAction, Vec<String>)> {
+            return KeyAction::ALL
+                .iter()
+                .map(|action| {
+                    let mut keys: Vec<String> = self
+                        .actions
+                        .iter()
+                        .filter(|(_, bound)| *bound == action)
+                        .map(|(binding, _)| binding.to_string())
+                        .collect();
+                    keys.sort();
+                    (*action, keys)
+                })
+                .collect();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::config_handler::{load_config, KeyAction, KeyBinding};
+    use crate::todo_backend::todo::{TodoError, DEFAULT_ARCHIVE_DAYS};
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use std::{env, fs, path::PathBuf};
+
+    //a config file of its own for every test, tests run at the same time
+    fn config_file(name: &str, contents: &str) -> PathBuf {
+        let file = env::temp_dir().join(format!("todo-list-test-{}-{name}.toml", std::process::id()));
+        fs::write(&file, contents).unwrap();
+        return file;
+    }
+
+    #[test]
+    fn archive_days_has_to_be_sane() {
+        for days in ["-1", "36501", "99999999999999"] {
+            let file = config_file("archive-days", &format!("archive_days = {days}"));
+            assert!(matches!(load_config(&file), Err(TodoError::Config { .. })), "{days}");
+        }
+        let file = config_file("archive-days", "archive_days = 0");
+        assert_eq!(load_config(&file).unwrap().archive_days, 0);
+        let file = config_file("archive-days", "");
+        assert_eq!(load_config(&file).unwrap().archive_days, DEFAULT_ARCHIVE_DAYS);
+        fs::remove_file(file).unwrap();
+    }
+
+    fn key(s: &str) -> String {
+        return s.parse::<KeyBinding>().unwrap().to_string();
+    }
+
+    //the reason load_config gives for the config file
+    fn config_error(name: &str, contents: &str) -> String {
+        let file = config_file(name, contents);
+        let reason = match load_config(&file) {
+            Err(TodoError::Config { reason, .. }) => reason,
+            _ => panic!("{contents} was accepted"),
+        };
+        fs::remove_file(file).unwrap();
+        return reason;
+    }
+
+    #[test]
+    fn keys_read_back_the_way_they_are_shown() {
+        for name in ["c", "C", "space", "enter", "ctrl+r", "alt+enter", "shift+tab", "ctrl+alt+delete", "f12", "+", "ctrl++"] {
+            assert_eq!(key(name), name);
+        }
+        assert_eq!(key("shift+c"), "C");
+        assert_eq!(key("Ctrl+Shift+Up"), "ctrl+shift+up");
+        assert_eq!(key("control+PageDown"), "ctrl+pgdn");
+        assert_eq!(key("backtab"), "shift+tab");
+        //what the terminal sends for ctrl+shift+c
+        let pressed = KeyEvent::new(KeyCode::Char('C'), KeyModifiers::CONTROL | KeyModifiers::SHIFT);
+        assert!("ctrl+shift+c".parse::<KeyBinding>().unwrap() == KeyBinding::from(pressed));
+        for name in ["", "super+c", "ctrl+", "f13", "pgupp", "ctrl+enterr"] {
+            assert!(name.parse::<KeyBinding>().is_err(), "{name}");
+        }
+    }
+
+    #[test]
+    fn keys_replace_the_defaults_of_their_action() {
+        let file = config_file("keys", "[keys]\ncomplete = [\"space\", \"enter\"]\ndelete = \"c\"\nundo = []");
+        let keys = load_config(&file).unwrap().keys;
+        let press = |code| keys.action(KeyEvent::new(code, KeyModifiers::NONE));
+        assert_eq!(press(KeyCode::Char(' ')), Some(KeyAction::Complete));
+        assert_eq!(press(KeyCode::Char('c')), Some(KeyAction::Delete));
+        assert_eq!(press(KeyCode::Char('x')), None);
+        assert_eq!(press(KeyCode::Char('U')), None);
+        fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn conflicting_keys_are_refused() {
+        assert_eq!(config_error("conflict", "[keys]\ndelete = \"c\""), "'c' is bound to both complete and delete");
+        assert_eq!(config_error("conflict-list", "[keys]\nquit = [\"q\", \"ctrl+r\"]"), "'ctrl+r' is bound to both quit and redo");
+        assert_eq!(config_error("unknown-action", "[keys]\nfly = \"w\""), "Unknown action 'fly' in [keys]");
+        assert!(config_error("unknown-key", "[keys]\nquit = \"hyper+q\"").starts_with("Unknown modifier 'hyper'"));
+    }
+}
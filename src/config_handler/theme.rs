@@ -0,0 +1,312 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use tui::style::{Color, Modifier, Style};
+
+pub const DEFAULT_THEME: &str = "dark";
+//used instead of the default theme when NO_COLOR is set
+pub const NO_COLOR_THEME: &str = "monochrome";
+pub const BUILT_IN_THEMES: [&str; 5] = ["dark", "light", "high-contrast", "solarized", "monochrome"];
+
+///The styles the TUI draws with
+#[derive(Clone, Copy)]
+pub struct Theme {
+    //the title bar, section titles and the help lines under the list
+    pub header: Style,
+    pub border: Style,
+    //items without a style of their own
+    pub list: Style,
+    pub overdue: Style,
+    pub completed: Style,
+    pub selected: Style,
+    pub error: Style,
+    //the command bar and the boxes new tasks and edits are typed into
+    pub input: Style,
+    pub project: Style,
+    pub tag: Style,
+    //from none to urgent
+    pub priorities: [Style; 5],
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        return Theme::built_in(DEFAULT_THEME).unwrap();
+    }
+}
+
+impl Theme {
+    pub fn built_in(name: &str) -> Option<Theme> {
+        let fg = |color: Color| Style::default().fg(color);
+        let plain = Style::default();
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        let theme = match name {
+            "dark" => Theme {
+                header: fg(Color::LightCyan),
+                border: fg(Color::White),
+                list: fg(Color::LightCyan),
+                overdue: fg(Color::Red),
+                completed: fg(Color::Gray),
+                selected: Style::default().add_modifier(Modifier::REVERSED),
+                error: fg(Color::Red),
+                input: fg(Color::LightGreen),
+                project: fg(Color::Magenta),
+                tag: fg(Color::LightBlue),
+                priorities: [
+                    fg(Color::LightCyan),
+                    fg(Color::Green),
+                    fg(Color::Yellow),
+                    fg(Color::LightRed),
+                    fg(Color::Red),
+                ],
+            },
+            "light" => Theme {
+                header: fg(Color::Blue),
+                border: fg(Color::Black),
+                list: fg(Color::Black),
+                overdue: fg(Color::Red).add_modifier(Modifier::BOLD),
+                completed: fg(Color::DarkGray),
+                selected: Style::default().fg(Color::White).bg(Color::Blue),
+                error: fg(Color::Red),
+                input: fg(Color::Green),
+                project: fg(Color::Magenta),
+                tag: fg(Color::Blue),
+                priorities: [
+                    fg(Color::Black),
+                    fg(Color::Green),
+                    fg(Color::Blue),
+                    fg(Color::Magenta),
+                    fg(Color::Red).add_modifier(Modifier::BOLD),
+                ],
+            },
+            "high-contrast" => Theme {
+                header: fg(Color::White).add_modifier(Modifier::BOLD),
+                border: fg(Color::White),
+                list: fg(Color::White),
+                overdue: fg(Color::LightRed).add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                completed: fg(Color::Gray),
+                selected: Style::default()
+                    .fg(Color::Black)
+                    .bg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+                error: Style::default()
+                    .fg(Color::White)
+                    .bg(Color::Red)
+                    .add_modifier(Modifier::BOLD),
+                input: fg(Color::Yellow).add_modifier(Modifier::BOLD),
+                project: fg(Color::LightMagenta).add_modifier(Modifier::BOLD),
+                tag: fg(Color::LightCyan).add_modifier(Modifier::BOLD),
+                priorities: [
+                    fg(Color::White),
+                    fg(Color::LightGreen),
+                    fg(Color::LightYellow),
+                    fg(Color::LightRed).add_modifier(Modifier::BOLD),
+                    fg(Color::LightRed).add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                ],
+            },
+            //the solarized palette on a dark background
+            "solarized" => {
+                let base0 = Color::Rgb(0x83, 0x94, 0x96);
+                let base01 = Color::Rgb(0x58, 0x6e, 0x75);
+                let yellow = Color::Rgb(0xb5, 0x89, 0x00);
+                let orange = Color::Rgb(0xcb, 0x4b, 0x16);
+                let red = Color::Rgb(0xdc, 0x32, 0x2f);
+                let magenta = Color::Rgb(0xd3, 0x36, 0x82);
+                let violet = Color::Rgb(0x6c, 0x71, 0xc4);
+                let blue = Color::Rgb(0x26, 0x8b, 0xd2);
+                let cyan = Color::Rgb(0x2a, 0xa1, 0x98);
+                let green = Color::Rgb(0x85, 0x99, 0x00);
+                Theme {
+                    header: fg(blue),
+                    border: fg(base01),
+                    list: fg(base0),
+                    overdue: fg(red),
+                    completed: fg(base01),
+                    selected: Style::default().fg(Color::Rgb(0x00, 0x2b, 0x36)).bg(cyan),
+                    error: fg(red),
+                    input: fg(green),
+                    project: fg(magenta),
+                    tag: fg(violet),
+                    priorities: [fg(base0), fg(green), fg(yellow), fg(orange), fg(red)],
+                }
+            }
+            //no colors at all, only bold, dim and reversed text
+            "monochrome" => Theme {
+                header: bold,
+                border: plain,
+                list: plain,
+                overdue: bold,
+                completed: Style::default().add_modifier(Modifier::DIM),
+                selected: Style::default().add_modifier(Modifier::REVERSED),
+                error: bold,
+                input: bold,
+                project: plain,
+                tag: plain,
+                priorities: [plain, plain, plain, bold, bold],
+            },
+            _ => return None,
+        };
+        return Some(theme);
+    }
+
+    ///A theme from the [themes.<name>] tables of the config file, a user theme can't use
+    ///another user theme as its base
+    pub fn find(name: &str, user_themes: &HashMap<String, ThemeSpec>) -> Result<Theme, String> {
+        let spec = match user_themes.get(name) {
+            Some(r) => r,
+            None => {
+                return Theme::built_in(name).ok_or_else(|| {
+                    format!("Unknown theme '{name}', expected {} or a theme from [themes]", BUILT_IN_THEMES.join(", "))
+                })
+            }
+        };
+        let base = spec.base.as_deref().unwrap_or(DEFAULT_THEME);
+        let mut theme = match Theme::built_in(base) {
+            Some(r) => r,
+            None => return Err(format!("Unknown base '{base}' for theme '{name}', expected {}", BUILT_IN_THEMES.join(", "))),
+        };
+        let fields = [
+            (&spec.header, &mut theme.header),
+            (&spec.border, &mut theme.border),
+            (&spec.list, &mut theme.list),
+            (&spec.overdue, &mut theme.overdue),
+            (&spec.completed, &mut theme.completed),
+            (&spec.selected, &mut theme.selected),
+            (&spec.error, &mut theme.error),
+            (&spec.input, &mut theme.input),
+            (&spec.project, &mut theme.project),
+            (&spec.tag, &mut theme.tag),
+        ];
+        for (value, style) in fields {
+            if let Some(value) = value {
+                *style = value.to_style()?;
+            }
+        }
+        let priorities = [
+            &spec.priority_none,
+            &spec.priority_low,
+            &spec.priority_medium,
+            &spec.priority_high,
+            &spec.priority_urgent,
+        ];
+        for (value, style) in priorities.into_iter().zip(theme.priorities.iter_mut()) {
+            if let Some(value) = value {
+                *style = value.to_style()?;
+            }
+        }
+        return Ok(theme);
+    }
+}
+
+///A theme as written in the config file, ex:
+///[themes.mine]
+///base = "light"
+///selected = { fg = "black", bg = "#ffd75f", bold = true }
+///overdue = "red"
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeSpec {
+    base: Option<String>,
+    header: Option<StyleSpec>,
+    border: Option<StyleSpec>,
+    list: Option<StyleSpec>,
+    overdue: Option<StyleSpec>,
+    completed: Option<StyleSpec>,
+    selected: Option<StyleSpec>,
+    error: Option<StyleSpec>,
+    input: Option<StyleSpec>,
+    project: Option<StyleSpec>,
+    tag: Option<StyleSpec>,
+    priority_none: Option<StyleSpec>,
+    priority_low: Option<StyleSpec>,
+    priority_medium: Option<StyleSpec>,
+    priority_high: Option<StyleSpec>,
+    priority_urgent: Option<StyleSpec>,
+}
+
+///Either just the text color or a table with colors and modifiers
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum StyleSpec {
+    Color(String),
+    Full {
+        fg: Option<String>,
+        bg: Option<String>,
+        #[serde(default)]
+        bold: bool,
+        #[serde(default)]
+        dim: bool,
+        #[serde(default)]
+        italic: bool,
+        #[serde(default)]
+        underlined: bool,
+        #[serde(default)]
+        reversed: bool,
+    },
+}
+
+impl StyleSpec {
+    fn to_style(&self) -> Result<Style, String> {
+        let (fg, bg, modifiers) = match self {
+            StyleSpec::Color(color) => (Some(color), None, Modifier::empty()),
+            StyleSpec::Full { fg, bg, bold, dim, italic, underlined, reversed } => {
+                let mut modifiers = Modifier::empty();
+                let flags = [
+                    (bold, Modifier::BOLD),
+                    (dim, Modifier::DIM),
+                    (italic, Modifier::ITALIC),
+                    (underlined, Modifier::UNDERLINED),
+                    (reversed, Modifier::REVERSED),
+                ];
+                for (set, modifier) in flags {
+                    if *set {
+                        modifiers |= modifier;
+                    }
+                }
+                (fg.as_ref(), bg.as_ref(), modifiers)
+            }
+        };
+        let mut style = Style::default().add_modifier(modifiers);
+        if let Some(color) = fg {
+            style = style.fg(parse_color(color)?);
+        }
+        if let Some(color) = bg {
+            style = style.bg(parse_color(color)?);
+        }
+        return Ok(style);
+    }
+}
+
+///A color name (ex: lightblue or light-blue), a hex color (ex: #268bd2) or a number from 0 to 255
+fn parse_color(color: &str) -> Result<Color, String> {
+    let name = color.to_lowercase().replace(['-', '_', ' '], "");
+    let parsed = match &*name {
+        "reset" | "default" => Color::Reset,
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" | "grey" => Color::Gray,
+        "darkgray" | "darkgrey" => Color::DarkGray,
+        "lightred" => Color::LightRed,
+        "lightgreen" => Color::LightGreen,
+        "lightyellow" => Color::LightYellow,
+        "lightblue" => Color::LightBlue,
+        "lightmagenta" => Color::LightMagenta,
+        "lightcyan" => Color::LightCyan,
+        "white" => Color::White,
+        hex if hex.starts_with('#') && hex.len() == 7 && hex.is_ascii() => {
+            let channel = |range| u8::from_str_radix(&hex[range], 16);
+            match (channel(1..3), channel(3..5), channel(5..7)) {
+                (Ok(r), Ok(g), Ok(b)) => Color::Rgb(r, g, b),
+                _ => return Err(format!("Invalid hex color '{color}'")),
+            }
+        }
+        number => match number.parse::<u8>() {
+            Ok(index) => Color::Indexed(index),
+            Err(_) => return Err(format!("Unknown color '{color}'")),
+        },
+    };
+    return Ok(parsed);
+}
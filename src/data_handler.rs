@@ -1,47 +1,555 @@
+pub mod schema;
+pub mod todo_txt;
+
 pub mod data_handler {
-    use crate::todo_backend::todo::{TodoList, TodoItem};
+    use super::schema::{from_current, from_versioned, to_versioned};
+    pub use super::todo_txt::{to_todo_txt, SkippedLine};
+    use super::todo_txt::from_todo_txt;
+    use crate::todo_backend::todo::{Archive, History, TodoItem, TodoList, TodoResult};
+    use crate::todo_backend::todo::TodoError;
+    use chrono::{Duration, NaiveDateTime};
+    use home::home_dir;
     use std::{
-        fs::File,
-        io::{self, Read, Write}, path::PathBuf,
+        cell::{Cell, RefCell},
+        env, fs,
+        fs::{File, OpenOptions},
+        io::{Read, Write}, path::{Path, PathBuf},
     };
 
+    pub const ARCHIVE_SUFFIX: &str = "_archive";
+    pub const HISTORY_SUFFIX: &str = "_history";
+    pub const BACKUP_SUFFIX: &str = "_backups";
+    //every program saving the list takes turns through an advisory lock on this file
+    pub const LOCK_SUFFIX: &str = "_lock";
+    //a list that couldn't be saved over another program's changes, ex: default.json_conflict-2023-05-12_17-00-00.123
+    pub const CONFLICT_SUFFIX: &str = "_conflict-";
+    //how many backups are kept unless configured otherwise
+    pub const DEFAULT_BACKUPS: usize = 10;
+    //backups are named after the time they were made, this sorts in the order they were made in
+    const BACKUP_NAME_FORMAT: &str = "%Y-%m-%d_%H-%M-%S%.3f";
+    //saves this soon after the newest backup aren't backed up, so commands run one after another don't push out older backups
+    pub const BACKUP_INTERVAL_MINUTES: i64 = 10;
+    //named lists are saved as <name>.json in the data directory
+    pub const LIST_EXTENSION: &str = "json";
+    pub const DEFAULT_LIST: &str = "default";
+    //the save file from before named lists, still used for the default list when it exists
+    const LEGACY_FILE: &str = ".todo_items";
+
+    ///Where named lists are kept, $XDG_DATA_HOME/todo-list or ~/.local/share/todo-list
+    pub fn data_dir() -> PathBuf {
+        let base = match env::var_os("XDG_DATA_HOME").map(PathBuf::from) {
+            //relative paths are meant to be ignored
+            Some(r) if r.is_absolute() => r,
+            _ => home_dir().expect("Could not find home directory").join(".local/share"),
+        };
+        return base.join("todo-list");
+    }
+
+    ///Names may only use letters, numbers, - and _, ex: team-sprint
+    pub fn check_list_name(name: &str) -> TodoResult<()> {
+        let valid = name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+        if name.is_empty() || !valid {
+            return Err(TodoError::invalid_input(
+                name,
+                "List names can only use letters, numbers, - and _",
+            ));
+        }
+        return Ok(());
+    }
 
-    pub fn load_todo_list(file: &PathBuf) -> io::Result<TodoList> {
+    ///The save file of a named list, ex: ~/.local/share/todo-list/work.json
+    pub fn list_path(name: &str) -> TodoResult<PathBuf> {
+        check_list_name(name)?;
+        let legacy = home_dir().expect("Could not find home directory").join(LEGACY_FILE);
+        let file = data_dir().join(format!("{name}.{LIST_EXTENSION}"));
+        if name == DEFAULT_LIST && !file.exists() && legacy.exists() {
+            return Ok(legacy);
+        }
+        fs::create_dir_all(data_dir())?;
+        return Ok(file);
+    }
+
+    ///Every named list in the data directory in alphabetical order, the default list is always there
+    pub fn list_names() -> TodoResult<Vec<String>> {
+        let mut names = vec![DEFAULT_LIST.to_owned()];
+        let entries = match fs::read_dir(data_dir()) {
+            Ok(r) => r,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(names),
+            Err(e) => return Err(e.into()),
+        };
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|extension| extension == LIST_EXTENSION) {
+                let name = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+                if check_list_name(&name).is_ok() && !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+        names.sort();
+        return Ok(names);
+    }
+
+    ///Where a list is saved and how, shared by the TUI's autosave and the save when the app quits.
+    ///Loading and saving hold the list's lock, and a save never overwrites changes another
+    ///program made since the list was loaded
+    pub struct Store {
+        pub file: PathBuf,
+        //how many backups are kept, view back_up
+        backups: usize,
+        persist_undo: bool,
+        //only the first save of a session can be backed up, so autosaving doesn't push out older backups
+        backed_up: Cell<bool>,
+        //the files as they were when last loaded or saved
+        on_disk: RefCell<Option<DiskState>>,
+        //the list and archive as they were when last loaded or saved, in the current format. Files that
+        //only need a newer version number aren't rewritten until the list changes
+        loaded: RefCell<Option<DiskState>>,
+        //the open lock file while the lock is held, view locked
+        lock: RefCell<Option<File>>,
+    }
+    impl Store {
+        pub fn new(file: PathBuf, backups: usize, persist_undo: bool) -> Store {
+            return Store {
+                file,
+                backups,
+                persist_undo,
+                backed_up: Cell::new(false),
+                on_disk: RefCell::new(None),
+                loaded: RefCell::new(None),
+                lock: RefCell::new(None),
+            };
+        }
+
+        ///Runs f while holding the lock on the list, other programs wait for it before loading or saving.
+        ///Calls inside f don't lock again
+        pub fn locked<T>(&self, f: impl FnOnce() -> TodoResult<T>) -> TodoResult<T> {
+            if self.lock.borrow().is_some() {
+                return f();
+            }
+            let lock_file = OpenOptions::new()
+                .create(true)
+                .truncate(false)
+                .write(true)
+                .open(sibling_path(&self.file, LOCK_SUFFIX))?;
+            lock_file.lock()?;
+            *self.lock.borrow_mut() = Some(lock_file);
+            let result = f();
+            //closing the lock file releases the lock
+            *self.lock.borrow_mut() = None;
+            return result;
+        }
+
+        ///Loads the list and its archive and remembers them, so later saves notice other programs' changes
+        pub fn load(&self) -> TodoResult<(TodoList, Archive)> {
+            return self.locked(|| {
+                let mut todo = load_todo_list(&self.file)?;
+                let archive = load_archive(&archive_path(&self.file))?;
+                todo.reserve_ids(archive.highest_id());
+                *self.on_disk.borrow_mut() = Some(DiskState::read(&self.file)?);
+                *self.loaded.borrow_mut() = Some(DiskState {
+                    list: Some(to_versioned(&todo)?),
+                    archive: Some(to_versioned(&archive)?),
+                });
+                return Ok((todo, archive));
+            });
+        }
+
+        ///Whether the files differ from how they were last loaded or saved
+        pub fn changed_on_disk(&self) -> TodoResult<bool> {
+            return match &*self.on_disk.borrow() {
+                Some(on_disk) => Ok(*on_disk != DiskState::read(&self.file)?),
+                None => Ok(false),
+            };
+        }
+
+        ///Saves the list unless another program changed it since it was loaded, that is a Conflict error.
+        ///Nothing is written when the list is the same as on disk
+        pub fn save(&self, todo: &TodoList, archive: &Archive, history: &History) -> TodoResult<()> {
+            return self.locked(|| self.write(todo, archive, history, false));
+        }
+
+        ///Saves the list even if another program changed it
+        pub fn overwrite(&self, todo: &TodoList, archive: &Archive, history: &History) -> TodoResult<()> {
+            return self.locked(|| self.write(todo, archive, history, true));
+        }
+
+        ///Writes the list and its archive next to the save file instead of over it, for when another
+        ///program changed it and nobody is left to ask which version to keep. Returns the copy's path
+        pub fn keep_conflict_copy(&self, todo: &TodoList, archive: &Archive) -> TodoResult<PathBuf> {
+            let name = chrono::Local::now().format(BACKUP_NAME_FORMAT);
+            let copy = sibling_path(&self.file, &format!("{CONFLICT_SUFFIX}{name}"));
+            write_atomically(&copy, &to_versioned(todo)?)?;
+            write_atomically(&archive_path(&copy), &to_versioned(archive)?)?;
+            return Ok(copy);
+        }
+
+        fn write(&self, todo: &TodoList, archive: &Archive, history: &History, overwrite: bool) -> TodoResult<()> {
+            let saved = DiskState {
+                list: Some(to_versioned(todo)?),
+                archive: Some(to_versioned(archive)?),
+            };
+            if self.loaded.borrow().as_ref() != Some(&saved) {
+                if !overwrite && self.changed_on_disk()? {
+                    return Err(TodoError::Conflict(self.file.display().to_string()));
+                }
+                if !self.backed_up.replace(true) && backup_due(&self.file)? {
+                    back_up(&self.file, todo, archive, self.backups)?;
+                }
+                save_todo_list(todo, self.file.clone())?;
+                save_archive(archive, &archive_path(&self.file))?;
+                *self.on_disk.borrow_mut() = Some(saved.clone());
+                *self.loaded.borrow_mut() = Some(saved);
+            }
+            if self.persist_undo {
+                save_history(history, &history_path(&self.file))?;
+            }
+            return Ok(());
+        }
+    }
+
+    ///The contents of a list's save file and archive, None for a file that doesn't exist
+    #[derive(PartialEq, Clone)]
+    struct DiskState {
+        list: Option<Vec<u8>>,
+        archive: Option<Vec<u8>>,
+    }
+    impl DiskState {
+        fn read(file: &Path) -> TodoResult<DiskState> {
+            let read = |path: &Path| match fs::read(path) {
+                Ok(r) => Ok(Some(r)),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(e),
+            };
+            return Ok(DiskState { list: read(file)?, archive: read(&archive_path(file))? });
+        }
+    }
+
+    ///Files from older versions are upgraded, view keep_pre_migration
+    pub fn load_todo_list(file: &PathBuf) -> TodoResult<TodoList> {
         if !file.exists() {
             generate_file(file)?;
         } 
-        let mut opened_file = File::open(file).expect("Failed to Open Save File");
+        let mut opened_file = File::open(file)?;
 
         let mut file_contents = String::new();
         opened_file.read_to_string(&mut file_contents)?;
 
-        let mut todo: TodoList = serde_json::from_str(&file_contents)?;
-        todo.completed_items = Vec::new();
+        let (mut todo, migrated): (TodoList, Option<u64>) = from_versioned(&file_contents, &file.display().to_string())?;
+        keep_pre_migration(file, &file_contents, migrated)?;
+        todo.assign_missing_ids();
         return Ok(todo);
     }
 
-    pub fn save_todo_list(todo_list: &TodoList, path: PathBuf) -> io::Result<()> {
-        let serialized_todo = serde_json::to_string(&todo_list)?;
-        let mut file = File::create(path)?;
-        file.write_all(serialized_todo.as_bytes())?;
+    pub fn save_todo_list(todo_list: &TodoList, path: PathBuf) -> TodoResult<()> {
+        return write_atomically(&path, &to_versioned(todo_list)?);
+    }
+
+    ///Keeps a file a migration changed as it was, ex: default.json_v0, since the next save replaces it
+    ///with the upgraded one. An earlier copy of the same version is left alone
+    fn keep_pre_migration(file: &Path, contents: &str, migrated: Option<u64>) -> TodoResult<()> {
+        let Some(version) = migrated else {
+            return Ok(());
+        };
+        let copy = sibling_path(file, &format!("_v{version}"));
+        if !copy.exists() {
+            write_atomically(&copy, contents.as_bytes())?;
+        }
         return Ok(());
     }
 
-    fn generate_file(file: &PathBuf) -> io::Result<()> {
-        let mut file = File::create(file)?;
-        match file.write_all(b"{\"todo_items\":[],\"completed_items\":[]}") {
-            Ok(_) => return Ok(()),
-            Err(e) => return Err(e),
+    ///The archive lives next to the save file, ex: ~/.todo_items_archive
+    pub fn archive_path(file: &Path) -> PathBuf {
+        return sibling_path(file, ARCHIVE_SUFFIX);
+    }
+
+    ///The undo history is only saved when asked for, ex: ~/.todo_items_history
+    pub fn history_path(file: &Path) -> PathBuf {
+        return sibling_path(file, HISTORY_SUFFIX);
+    }
+
+    pub fn load_archive(file: &Path) -> TodoResult<Archive> {
+        if !file.exists() {
+            return Ok(Archive::default());
         }
+        let mut file_contents = String::new();
+        File::open(file)?.read_to_string(&mut file_contents)?;
+
+        let (archive, migrated) = from_versioned(&file_contents, &file.display().to_string())?;
+        keep_pre_migration(file, &file_contents, migrated)?;
+        return Ok(archive);
     }
 
-    pub fn handle_data_errors(e: io::Error) -> io::Result<()> {
-        match e.kind() {
-            io::ErrorKind::PermissionDenied => {
-                println!("App does not have permission to access save file");
-                return Err(e);
+    pub fn save_archive(archive: &Archive, path: &Path) -> TodoResult<()> {
+        return write_atomically(path, &to_versioned(archive)?);
+    }
+
+    pub fn load_history(file: &Path, depth: usize) -> TodoResult<History> {
+        if !file.exists() {
+            return Ok(History::new(depth));
+        }
+        let mut file_contents = String::new();
+        File::open(file)?.read_to_string(&mut file_contents)?;
+
+        //undo steps aren't migrated, a history from another version or one that can't be read starts over
+        let mut history: History = match from_current(&file_contents) {
+            Ok(Some(r)) => r,
+            _ => return Ok(History::new(depth)),
+        };
+        history.set_depth(depth);
+        return Ok(history);
+    }
+
+    pub fn save_history(history: &History, path: &Path) -> TodoResult<()> {
+        return write_atomically(path, &to_versioned(history)?);
+    }
+
+    ///Reads the tasks in a todo.txt file, view from_todo_txt
+    pub fn import_todo_txt(path: &Path) -> TodoResult<(Vec<TodoItem>, Vec<SkippedLine>)> {
+        let contents = fs::read_to_string(path)?;
+        return Ok(from_todo_txt(&contents, chrono::Local::now().naive_local()));
+    }
+
+    pub fn export_todo_txt(todo: &TodoList, path: &Path) -> TodoResult<()> {
+        return write_atomically(path, to_todo_txt(todo).as_bytes());
+    }
+
+    ///Writes into a temporary file next to path and renames it over path once it is on disk,
+    ///so a crash or a full disk leaves either the old or the new file but never half of one
+    fn write_atomically(path: &Path, contents: &[u8]) -> TodoResult<()> {
+        let mut temp_name = std::ffi::OsString::from(".");
+        temp_name.push(path.file_name().unwrap_or_default());
+        temp_name.push(format!(".{}.tmp", std::process::id()));
+        let temp_path = path.with_file_name(temp_name);
+
+        let written = File::create(&temp_path).and_then(|mut file| {
+            file.write_all(contents)?;
+            file.sync_all()
+        });
+        if let Err(e) = written.and_then(|_| fs::rename(&temp_path, path)) {
+            let _ = fs::remove_file(&temp_path);
+            return Err(e.into());
+        }
+        //the rename itself is only durable once the directory is synced
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            File::open(dir)?.sync_all()?;
+        }
+        return Ok(());
+    }
+
+    ///A copy of the list and its archive from before a save, ex: default.json_backups/2023-05-12_17-00-00.123
+    pub struct Backup {
+        pub name: String,
+        list: PathBuf,
+        archive: PathBuf,
+    }
+
+    fn backup_dir(file: &Path) -> PathBuf {
+        return sibling_path(file, BACKUP_SUFFIX);
+    }
+
+    ///Copies the saved list and archive into the backup directory if saving todo and archive would
+    ///change them and they aren't the same as in the newest backup, then deletes all but the newest
+    ///keep backups. keep = 0 turns backups off. Returns whether a backup was made
+    pub fn back_up(file: &Path, todo: &TodoList, archive: &Archive, keep: usize) -> TodoResult<bool> {
+        if keep == 0 || !file.exists() {
+            return Ok(false);
+        }
+        let archive_file = archive_path(file);
+        let saved = DiskState::read(file)?;
+        //a missing archive is an empty one
+        let empty_archive = to_versioned(&Archive::default())?;
+        let unchanged = saved.list == Some(to_versioned(todo)?)
+            && saved.archive.as_ref().unwrap_or(&empty_archive) == &to_versioned(archive)?;
+        let backed_up = match list_backups(file)?.first() {
+            Some(newest) => saved == DiskState { list: fs::read(&newest.list).ok(), archive: fs::read(&newest.archive).ok() },
+            None => false,
+        };
+        if unchanged || backed_up {
+            return Ok(false);
+        }
+
+        let dir = backup_dir(file);
+        fs::create_dir_all(&dir)?;
+        let name = chrono::Local::now().format(BACKUP_NAME_FORMAT).to_string();
+        write_atomically(&dir.join(&name), &fs::read(file)?)?;
+        if archive_file.exists() {
+            write_atomically(&sibling_path(&dir.join(&name), ARCHIVE_SUFFIX), &fs::read(&archive_file)?)?;
+        }
+
+        for old in list_backups(file)?.iter().skip(keep) {
+            fs::remove_file(&old.list)?;
+            if old.archive.exists() {
+                fs::remove_file(&old.archive)?;
+            }
+        }
+        return Ok(true);
+    }
+
+    ///Whether the newest backup of file is at least BACKUP_INTERVAL_MINUTES old, or there is none.
+    ///Backups with a time from the future don't count, the clock was changed since
+    fn backup_due(file: &Path) -> TodoResult<bool> {
+        let now = chrono::Local::now().naive_local();
+        let newest = list_backups(file)?
+            .iter()
+            .filter_map(|backup| NaiveDateTime::parse_from_str(&backup.name, BACKUP_NAME_FORMAT).ok())
+            .find(|made| *made <= now);
+        return Ok(newest.is_none_or(|made| now - made >= Duration::minutes(BACKUP_INTERVAL_MINUTES)));
+    }
+
+    ///The backups of file, newest first
+    pub fn list_backups(file: &Path) -> TodoResult<Vec<Backup>> {
+        let dir = backup_dir(file);
+        let entries = match fs::read_dir(&dir) {
+            Ok(r) => r,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut backups = Vec::new();
+        for entry in entries {
+            let name = entry?.file_name().to_string_lossy().into_owned();
+            if name.ends_with(ARCHIVE_SUFFIX) || name.starts_with('.') {
+                continue;
             }
-            _ => return Err(e),
+            let list = dir.join(&name);
+            backups.push(Backup { archive: sibling_path(&list, ARCHIVE_SUFFIX), list, name });
+        }
+        //the names are timestamps, so sorting them sorts by age
+        backups.sort_by(|a, b| b.name.cmp(&a.name));
+        return Ok(backups);
+    }
+
+    ///The list and archive in a backup, backups made without an archive come with an empty one.
+    ///Backups from older versions are upgraded without keeping a copy, they are one already
+    pub fn load_backup(backup: &Backup) -> TodoResult<(TodoList, Archive)> {
+        let (mut todo, _): (TodoList, Option<u64>) =
+            from_versioned(&fs::read_to_string(&backup.list)?, &backup.list.display().to_string())?;
+        todo.assign_missing_ids();
+        let archive = match backup.archive.exists() {
+            true => from_versioned(&fs::read_to_string(&backup.archive)?, &backup.archive.display().to_string())?.0,
+            false => Archive::default(),
+        };
+        return Ok((todo, archive));
+    }
+
+    fn sibling_path(file: &Path, suffix: &str) -> PathBuf {
+        let mut name = file.file_name().unwrap_or_default().to_owned();
+        name.push(suffix);
+        return file.with_file_name(name);
+    }
+
+    fn generate_file(file: &PathBuf) -> TodoResult<()> {
+        let mut file = File::create(file)?;
+        file.write_all(&to_versioned(&TodoList::new())?)?;
+        return Ok(());
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::{archive_path, back_up, backup_dir, backup_due, list_backups, write_atomically, BACKUP_NAME_FORMAT};
+        use crate::data_handler::schema::to_versioned;
+        use crate::todo_backend::todo::{Archive, TodoList};
+        use chrono::Duration;
+        use std::{env, fs, path::{Path, PathBuf}};
+
+        //an empty directory of its own for every test, tests run at the same time. Returns the save file in it
+        fn save_file(name: &str) -> PathBuf {
+            let dir = env::temp_dir().join(format!("todo-list-test-{}-{name}", std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            return dir.join("list.json");
+        }
+
+        fn list_with(titles: &[&str]) -> TodoList {
+            let mut todo = TodoList::new();
+            for title in titles {
+                todo.add_item(title).unwrap();
+            }
+            return todo;
+        }
+
+        fn save(file: &Path, todo: &TodoList, archive: &Archive) {
+            write_atomically(file, &to_versioned(todo).unwrap()).unwrap();
+            write_atomically(&archive_path(file), &to_versioned(archive).unwrap()).unwrap();
+        }
+
+        #[test]
+        fn writing_replaces_the_whole_file() {
+            let file = save_file("write");
+            write_atomically(&file, b"a longer first version").unwrap();
+            write_atomically(&file, b"second").unwrap();
+            assert_eq!(fs::read(&file).unwrap(), b"second");
+            //the temporary file is renamed, not left behind
+            assert_eq!(fs::read_dir(file.parent().unwrap()).unwrap().count(), 1);
+            fs::remove_dir_all(file.parent().unwrap()).unwrap();
+        }
+
+        #[test]
+        fn only_changes_are_backed_up() {
+            let file = save_file("changes");
+            let (a, ab, ac) = (list_with(&["a"]), list_with(&["a", "b"]), list_with(&["a", "c"]));
+            let archive = Archive::default();
+            //nothing saved yet, nothing to back up
+            assert!(!back_up(&file, &a, &archive, 5).unwrap());
+            write_atomically(&file, &to_versioned(&a).unwrap()).unwrap();
+            //a missing archive is the same as an empty one
+            assert!(!back_up(&file, &a, &archive, 5).unwrap());
+            assert!(back_up(&file, &ab, &archive, 5).unwrap());
+            //the saved list is the same as in the newest backup
+            assert!(!back_up(&file, &ac, &archive, 5).unwrap());
+            assert!(!back_up(&file, &ab, &archive, 0).unwrap());
+            let backups = list_backups(&file).unwrap();
+            assert_eq!(backups.len(), 1);
+            assert!(!backups[0].archive.exists());
+            fs::remove_dir_all(file.parent().unwrap()).unwrap();
+        }
+
+        #[test]
+        fn only_the_newest_backups_are_kept() {
+            let file = save_file("pruning");
+            let archive = Archive::default();
+            let lists = [list_with(&["a"]), list_with(&["a", "b"]), list_with(&["a", "b", "c"])];
+            for (saved, next) in lists.iter().zip(&lists[1..]) {
+                save(&file, saved, &archive);
+                assert!(back_up(&file, next, &archive, 2).unwrap());
+                //backups are named after the millisecond they were made in
+                std::thread::sleep(std::time::Duration::from_millis(5));
+            }
+            save(&file, &lists[2], &archive);
+            assert!(back_up(&file, &TodoList::new(), &archive, 2).unwrap());
+
+            let backups = list_backups(&file).unwrap();
+            assert_eq!(backups.len(), 2);
+            assert_eq!(fs::read(&backups[0].list).unwrap(), to_versioned(&lists[2]).unwrap());
+            assert_eq!(fs::read(&backups[1].list).unwrap(), to_versioned(&lists[1]).unwrap());
+            assert_eq!(fs::read(&backups[0].archive).unwrap(), to_versioned(&archive).unwrap());
+            //the archives go along with their lists
+            assert_eq!(fs::read_dir(backup_dir(&file)).unwrap().count(), 4);
+            fs::remove_dir_all(file.parent().unwrap()).unwrap();
+        }
+
+        #[test]
+        fn backups_are_made_minutes_apart() {
+            let file = save_file("interval");
+            assert!(backup_due(&file).unwrap());
+            save(&file, &list_with(&["a"]), &Archive::default());
+            assert!(back_up(&file, &TodoList::new(), &Archive::default(), 5).unwrap());
+            assert!(!backup_due(&file).unwrap());
+
+            fs::remove_dir_all(backup_dir(&file)).unwrap();
+            fs::create_dir_all(backup_dir(&file)).unwrap();
+            let now = chrono::Local::now().naive_local();
+            let made_at = |minutes: i64| (now + Duration::minutes(minutes)).format(BACKUP_NAME_FORMAT).to_string();
+            fs::write(backup_dir(&file).join(made_at(-60)), "").unwrap();
+            assert!(backup_due(&file).unwrap());
+            //a backup from the future is ignored
+            fs::write(backup_dir(&file).join(made_at(60)), "").unwrap();
+            assert!(backup_due(&file).unwrap());
+            fs::write(backup_dir(&file).join(made_at(-1)), "").unwrap();
+            assert!(!backup_due(&file).unwrap());
+            fs::remove_dir_all(file.parent().unwrap()).unwrap();
         }
     }
 }
@@ -0,0 +1,108 @@
+use crate::todo_backend::todo::{TodoError, TodoResult};
+use serde::de::{DeserializeOwned, Error};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+///The version of the save format written by this build, raise it and add a migration
+///whenever a change would keep older builds from reading the files correctly
+pub const SCHEMA_VERSION: u64 = 1;
+
+//changes the fields of a file in place to those of the next version, returns whether anything changed
+type Migration = fn(&mut Map<String, Value>) -> TodoResult<bool>;
+
+//MIGRATIONS[n] upgrades a file from version n to n + 1, files without a version are version 0
+const MIGRATIONS: [Migration; SCHEMA_VERSION as usize] = [unversioned];
+
+//every field added before versioning has a default, so these files only need the version
+fn unversioned(_file: &mut Map<String, Value>) -> TodoResult<bool> {
+    return Ok(false);
+}
+
+#[derive(Serialize)]
+struct Versioned<'a, T> {
+    version: u64,
+    #[serde(flatten)]
+    data: &'a T,
+}
+
+///The save file contents with the version as their first field
+pub fn to_versioned<T: Serialize>(data: &T) -> TodoResult<Vec<u8>> {
+    return Ok(serde_json::to_vec(&Versioned { version: SCHEMA_VERSION, data })?);
+}
+
+///Reads a save file of any version up to SCHEMA_VERSION, upgrading older ones on the way.
+///Also returns the version the file had when a migration changed it, files from newer builds are refused
+pub fn from_versioned<T: DeserializeOwned>(contents: &str, path: &str) -> TodoResult<(T, Option<u64>)> {
+    let (mut file, version) = split_version(contents)?;
+    if version > SCHEMA_VERSION {
+        return Err(TodoError::NewerVersion { file: path.to_owned(), version, supported: SCHEMA_VERSION });
+    }
+    let mut migrated = false;
+    for migration in &MIGRATIONS[version as usize..] {
+        migrated |= migration(&mut file)?;
+    }
+    return Ok((serde_json::from_value(Value::Object(file))?, migrated.then_some(version)));
+}
+
+///Reads a file only if it has the current version, for files that are fine to lose like the undo history
+pub fn from_current<T: DeserializeOwned>(contents: &str) -> TodoResult<Option<T>> {
+    let (file, version) = split_version(contents)?;
+    if version != SCHEMA_VERSION {
+        return Ok(None);
+    }
+    return Ok(Some(serde_json::from_value(Value::Object(file))?));
+}
+
+fn split_version(contents: &str) -> TodoResult<(Map<String, Value>, u64)> {
+    let mut file = match serde_json::from_str(contents)? {
+        Value::Object(fields) => fields,
+        _ => return Err(TodoError::Serialization(serde_json::Error::custom("expected an object"))),
+    };
+    let version = match file.remove("version").map(|r| r.as_u64()) {
+        None => 0,
+        Some(Some(r)) => r,
+        Some(None) => return Err(TodoError::Serialization(serde_json::Error::custom("version should be a whole number"))),
+    };
+    return Ok((file, version));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_current, from_versioned, to_versioned, SCHEMA_VERSION};
+    use crate::todo_backend::todo::{TodoError, TodoList};
+
+    #[test]
+    fn saves_the_version_first() {
+        let contents = String::from_utf8(to_versioned(&TodoList::new()).unwrap()).unwrap();
+        assert!(contents.starts_with(&format!("{{\"version\":{SCHEMA_VERSION},")));
+        let (todo, migrated) = from_versioned::<TodoList>(&contents, "list.json").unwrap();
+        assert!(todo == TodoList::new());
+        assert_eq!(migrated, None);
+    }
+
+    #[test]
+    fn upgrades_files_without_a_version() {
+        let contents = r#"{"next_id":2,"todo_items":[{"id":1,"title":"Old","completed":false,"due_date":null,
+            "completed_at":null,"priority":"None","children":[]}],"completed_items":[]}"#;
+        //only the version is missing, so there is nothing worth keeping a copy of
+        let (todo, migrated) = from_versioned::<TodoList>(contents, "list.json").unwrap();
+        assert_eq!(migrated, None);
+        assert_eq!(todo.todo_items[0].title, "Old");
+        assert_eq!(todo.todo_items[0].created_at, None);
+        //the undo history from another version is dropped instead
+        assert!(from_current::<TodoList>(contents).unwrap().is_none());
+    }
+
+    #[test]
+    fn refuses_newer_files() {
+        let newer = SCHEMA_VERSION + 1;
+        let contents = format!(r#"{{"version":{newer},"next_id":1,"todo_items":[],"completed_items":[],"new_field":1}}"#);
+        match from_versioned::<TodoList>(&contents, "list.json") {
+            Err(TodoError::NewerVersion { file, version, supported }) => {
+                assert_eq!((file.as_str(), version, supported), ("list.json", newer, SCHEMA_VERSION));
+            }
+            _ => panic!("a newer file was read"),
+        }
+        assert!(from_versioned::<TodoList>(r#"{"version":"2"}"#, "list.json").is_err());
+    }
+}
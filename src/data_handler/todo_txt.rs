@@ -0,0 +1,398 @@
+use crate::todo_backend::due_date::{parse_due_date, DEFAULT_DUE_TIME};
+use crate::todo_backend::todo::{Priority, Recurrence, TodoError, TodoItem, TodoList};
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime};
+use std::collections::HashMap;
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+///A line import left out, number counts from 1
+pub struct SkippedLine {
+    pub number: usize,
+    pub line: String,
+    pub reason: String,
+}
+
+///Writes the list in the todo.txt format, ex: "(A) 2023-05-01 Call mom +family @phone due:2023-05-14".
+///Open tasks come first. Subtasks follow their parent with parent:<id> and the parent gets id:<id>,
+///repeats todo.txt has no rec: for are written as repeat:<rule>
+pub fn to_todo_txt(todo: &TodoList) -> String {
+    let mut lines = Vec::new();
+    todo.todo_items
+        .iter()
+        .chain(todo.completed_items.iter())
+        .for_each(|item| write_item(item, None, &mut lines));
+    return lines.into_iter().map(|line| line + "\n").collect();
+}
+
+fn write_item(item: &TodoItem, parent: Option<usize>, lines: &mut Vec<String>) {
+    let date = |date: NaiveDateTime| date.format(DATE_FORMAT).to_string();
+    let mut words = Vec::new();
+    //completed tasks start with x and their completion date, priorities move to pri:
+    match (item.completed, priority_letter(item.priority)) {
+        (true, _) => {
+            words.push("x".to_owned());
+            //the creation date can only be given after a completion date
+            if let Some(completed_at) = item.completed_at {
+                words.push(date(completed_at));
+                words.extend(item.created_at.map(date));
+            }
+        }
+        (false, Some(letter)) => {
+            words.push(format!("({letter})"));
+            words.extend(item.created_at.map(date));
+        }
+        (false, None) => words.extend(item.created_at.map(date)),
+    }
+    words.push(item.title.clone());
+    words.extend(item.project.iter().map(|project| format!("+{project}")));
+    words.extend(item.tags.iter().map(|tag| format!("@{tag}")));
+    if let Some(due_date) = item.due_date {
+        let default_time = NaiveTime::from_hms_opt(DEFAULT_DUE_TIME.0, DEFAULT_DUE_TIME.1, 0);
+        match Some(due_date.time()) == default_time {
+            true => words.push(format!("due:{}", date(due_date))),
+            false => words.push(format!("due:{}", due_date.format("%Y-%m-%dT%H:%M"))),
+        }
+    }
+    words.extend(item.recurrence.as_ref().map(|rule| write_recurrence(rule, item.due_date)));
+    if let (true, Some(letter)) = (item.completed, priority_letter(item.priority)) {
+        words.push(format!("pri:{letter}"));
+    }
+    if !item.children.is_empty() {
+        words.push(format!("id:{}", item.id));
+    }
+    words.extend(parent.map(|parent| format!("parent:{parent}")));
+    lines.push(words.join(" "));
+
+    item.children
+        .iter()
+        .for_each(|child| write_item(child, Some(item.id), lines));
+}
+
+fn priority_letter(priority: Priority) -> Option<char> {
+    return match priority {
+        Priority::None => None,
+        Priority::Low => Some('D'),
+        Priority::Medium => Some('C'),
+        Priority::High => Some('B'),
+        Priority::Urgent => Some('A'),
+    };
+}
+
+//rec: without + counts from the completion, with + from the due date
+fn write_recurrence(recurrence: &Recurrence, due_date: Option<NaiveDateTime>) -> String {
+    return match recurrence {
+        Recurrence::Daily => "rec:+1d".to_owned(),
+        Recurrence::AfterCompletion(days) => format!("rec:{days}d"),
+        Recurrence::Weekly(weekdays) if due_date.is_some_and(|due| *weekdays == [due.weekday()]) => {
+            "rec:+1w".to_owned()
+        }
+        Recurrence::Monthly(day) if due_date.is_some_and(|due| due.day() == *day) => "rec:+1m".to_owned(),
+        //ex: repeat:weekly,mon,fri, the rule with commas instead of spaces
+        rule => format!("repeat:{}", rule.to_string().replace(' ', ",")),
+    };
+}
+
+///Reads tasks in the todo.txt format, ex: "x 2023-05-12 2023-05-01 Call mom +family @phone due:2023-05-14".
+///The last +project becomes the project and @contexts become tags, key:values other than due:, rec:,
+///repeat:, pri:, id: and parent: stay in the title. The items have no ids yet.
+///Lines with a date or value that can't be read are skipped and returned
+pub fn from_todo_txt(contents: &str, now: NaiveDateTime) -> (Vec<TodoItem>, Vec<SkippedLine>) {
+    let mut parsed = Vec::new();
+    let mut skipped = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse_line(line, now) {
+            Ok(r) => parsed.push(r),
+            Err(reason) => skipped.push(SkippedLine { number: index + 1, line: line.to_owned(), reason }),
+        }
+    }
+    return (nest(parsed), skipped);
+}
+
+//an item with its id: and parent: values, they only mean something inside the imported file
+struct ParsedLine {
+    item: TodoItem,
+    id: Option<String>,
+    parent: Option<String>,
+}
+
+fn parse_line(line: &str, now: NaiveDateTime) -> Result<ParsedLine, String> {
+    let mut words = line.split_whitespace().peekable();
+    let mut item = TodoItem::new(0, String::new(), None);
+    item.created_at = None;
+
+    item.completed = words.next_if_eq(&"x").is_some();
+    if let Some(letter) = words.peek().and_then(|word| parse_priority(word)) {
+        item.priority = letter;
+        words.next();
+    }
+    //completed tasks have their completion date first, then their creation date. Open tasks only
+    //have a creation date, a second one is where the description starts
+    let date_count = match item.completed {
+        true => 2,
+        false => 1,
+    };
+    let mut dates = Vec::new();
+    while dates.len() < date_count {
+        match words.peek().map(|word| parse_date(word)) {
+            Some(Some(date)) => dates.push(date?),
+            _ => break,
+        }
+        words.next();
+    }
+    match (item.completed, dates.as_slice()) {
+        (true, [completed_at, rest @ ..]) => {
+            item.completed_at = Some(*completed_at);
+            item.created_at = rest.first().copied();
+        }
+        (false, [created_at]) => item.created_at = Some(*created_at),
+        _ => {}
+    }
+
+    //the last one, since export writes the project after the title
+    let words: Vec<&str> = words.collect();
+    let project = words.iter().rposition(|word| word.len() > 1 && word.starts_with('+'));
+    let (mut id, mut parent) = (None, None);
+    let mut title_words = Vec::new();
+    //rec: words with where they were in the title, read once the due date is known
+    let mut recs = Vec::new();
+    for (index, word) in words.into_iter().enumerate() {
+        match word.split_once(':') {
+            Some(("due", value)) => {
+                let due_date = parse_due_date(value, now).map_err(|e| e.to_string())?;
+                item.due_date = Some(due_date);
+                continue;
+            }
+            Some(("rec", value)) => {
+                recs.push((title_words.len(), word, value));
+                continue;
+            }
+            Some(("repeat", value)) => {
+                item.recurrence = Some(value.parse().map_err(|e: TodoError| e.to_string())?);
+                continue;
+            }
+            Some(("pri", value)) => match parse_priority(&format!("({value})")) {
+                Some(priority) => {
+                    item.priority = priority;
+                    continue;
+                }
+                None => return Err(format!("Invalid priority '{word}', expected pri:A to pri:Z")),
+            },
+            Some(("id", value)) if !value.is_empty() => {
+                id = Some(value.to_owned());
+                continue;
+            }
+            Some(("parent", value)) if !value.is_empty() => {
+                parent = Some(value.to_owned());
+                continue;
+            }
+            _ => {}
+        }
+        match word.strip_prefix('@') {
+            //other projects stay in the title
+            _ if Some(index) == project => item.project = Some(word[1..].to_owned()),
+            Some(tag) if !tag.is_empty() => {
+                if !item.tags.iter().any(|r| r == tag) {
+                    item.tags.push(tag.to_owned());
+                }
+            }
+            _ => title_words.push(word),
+        }
+    }
+    //rec: can come before due:, +1w and +1m repeat on the weekday and day of the due date
+    let mut inserted = 0;
+    for (position, word, value) in recs {
+        match parse_rec(value, item.due_date.unwrap_or(now)) {
+            Some(recurrence) => item.recurrence = Some(recurrence),
+            //rules like every 2 weeks from the due date have no matching repeat, they stay in the title
+            None => {
+                title_words.insert(position + inserted, word);
+                inserted += 1;
+            }
+        }
+    }
+    if title_words.is_empty() {
+        return Err("The task has no description".to_owned());
+    }
+    item.title = title_words.join(" ");
+    return Ok(ParsedLine { item, id, parent });
+}
+
+//(A) is the most important, everything after (D) is low
+fn parse_priority(word: &str) -> Option<Priority> {
+    return match word.as_bytes() {
+        [b'(', b'A', b')'] => Some(Priority::Urgent),
+        [b'(', b'B', b')'] => Some(Priority::High),
+        [b'(', b'C', b')'] => Some(Priority::Medium),
+        [b'(', letter, b')'] if letter.is_ascii_uppercase() => Some(Priority::Low),
+        _ => None,
+    };
+}
+
+//None for words that aren't shaped like a date, an error for ones that are but aren't valid
+fn parse_date(word: &str) -> Option<Result<NaiveDateTime, String>> {
+    let shaped = word.len() == 10
+        && word.char_indices().all(|(index, c)| match index {
+            4 | 7 => c == '-',
+            _ => c.is_ascii_digit(),
+        });
+    if !shaped {
+        return None;
+    }
+    return Some(
+        NaiveDate::parse_from_str(word, DATE_FORMAT)
+            .map(|date| date.and_time(NaiveTime::MIN))
+            .map_err(|_| format!("Invalid date '{word}'")),
+    );
+}
+
+//the rec: rules that have a matching repeat, ex: +1d, 3d, 2w, +1w or +1m
+fn parse_rec(value: &str, due_date: NaiveDateTime) -> Option<Recurrence> {
+    let (from_due_date, rule) = match value.strip_prefix('+') {
+        Some(rule) => (true, rule),
+        None => (false, value),
+    };
+    let number = rule.get(..rule.len().checked_sub(1)?)?.parse::<u32>().ok().filter(|r| *r > 0)?;
+    return match (from_due_date, number, rule.chars().last()?) {
+        (true, 1, 'd') => Some(Recurrence::Daily),
+        (true, 1, 'w') => Some(Recurrence::Weekly(vec![due_date.weekday()])),
+        (true, 1, 'm') => Some(Recurrence::Monthly(due_date.day())),
+        (false, days, 'd') => Some(Recurrence::AfterCompletion(days)),
+        (false, weeks, 'w') => Some(Recurrence::AfterCompletion(weeks * 7)),
+        _ => None,
+    };
+}
+
+///Puts items with parent:<id> below the item with id:<id>, in the order they were in.
+///Items whose parent isn't there stay at the top
+fn nest(parsed: Vec<ParsedLine>) -> Vec<TodoItem> {
+    let ids: HashMap<&str, usize> = parsed
+        .iter()
+        .enumerate()
+        .filter_map(|(index, line)| line.id.as_deref().map(|id| (id, index)))
+        .collect();
+    let mut parents: Vec<Option<usize>> = parsed
+        .iter()
+        .map(|line| line.parent.as_deref().and_then(|parent| ids.get(parent).copied()))
+        .collect();
+    //an item that ends up being its own ancestor goes to the top instead
+    for index in 0..parents.len() {
+        let mut ancestor = parents[index];
+        for _ in 0..parents.len() {
+            match ancestor {
+                Some(r) if r == index => {
+                    parents[index] = None;
+                    break;
+                }
+                Some(r) => ancestor = parents[r],
+                None => break,
+            }
+        }
+    }
+    let depth = |mut index: usize| {
+        let mut depth = 0;
+        while let Some(parent) = parents[index] {
+            depth += 1;
+            index = parent;
+        }
+        return depth;
+    };
+
+    //the deepest items move first, so every item carries its subtasks when it moves
+    let mut order: Vec<usize> = (0..parsed.len()).collect();
+    order.sort_by_key(|index| std::cmp::Reverse(depth(*index)));
+    let mut items: Vec<Option<TodoItem>> = parsed.into_iter().map(|line| Some(line.item)).collect();
+    for index in order {
+        if let Some(parent) = parents[index] {
+            let item = items[index].take();
+            items[parent].as_mut().unwrap().children.extend(item);
+        }
+    }
+    return items.into_iter().flatten().collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_todo_txt, to_todo_txt};
+    use crate::todo_backend::todo::{Priority, Recurrence, TodoList};
+    use chrono::{NaiveDate, NaiveDateTime, Weekday};
+
+    fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        return NaiveDate::from_ymd_opt(year, month, day).unwrap().and_hms_opt(hour, minute, 0).unwrap();
+    }
+
+    fn import(contents: &str) -> TodoList {
+        let (items, skipped) = from_todo_txt(contents, at(2023, 5, 12, 17, 0));
+        assert!(skipped.is_empty());
+        let mut todo = TodoList::new();
+        todo.add_imported(items);
+        return todo;
+    }
+
+    #[test]
+    fn export_then_import_keeps_the_list() {
+        let mut todo = TodoList::new();
+        let call = todo.add_item("Call mom").unwrap();
+        todo.set_priority(call, Priority::Urgent).unwrap();
+        todo.set_project(call, Some("family".to_owned())).unwrap();
+        todo.set_tags(call, vec!["phone".to_owned(), "evening".to_owned()]).unwrap();
+        todo.set_due_date(call, Some(at(2023, 5, 14, 23, 59))).unwrap();
+        let water = todo.add_item("Water plants").unwrap();
+        todo.set_due_date(water, Some(at(2023, 5, 19, 9, 30))).unwrap();
+        todo.set_recurrence(water, Some(Recurrence::Weekly(vec![Weekday::Fri]))).unwrap();
+        let report = todo.add_item("Write report").unwrap();
+        todo.set_recurrence(report, Some(Recurrence::Weekly(vec![Weekday::Mon, Weekday::Thu]))).unwrap();
+        todo.add_subtask(report, "Outline").unwrap();
+        let done = todo.add_item("File taxes").unwrap();
+        todo.set_priority(done, Priority::High).unwrap();
+        todo.complete_item(done, false).unwrap();
+
+        let exported = to_todo_txt(&todo);
+        let imported = import(&exported);
+        assert_eq!(to_todo_txt(&imported), exported);
+
+        let call = &imported.todo_items[0];
+        assert_eq!((call.priority, call.project.as_deref()), (Priority::Urgent, Some("family")));
+        assert_eq!(call.tags, ["phone", "evening"]);
+        assert_eq!(call.due_date, Some(at(2023, 5, 14, 23, 59)));
+        assert_eq!(imported.todo_items[1].recurrence, Some(Recurrence::Weekly(vec![Weekday::Fri])));
+        assert_eq!(imported.todo_items[2].children[0].title, "Outline");
+        let done = &imported.completed_items[0];
+        assert!(done.completed && done.completed_at.is_some());
+        assert_eq!(done.priority, Priority::High);
+    }
+
+    #[test]
+    fn rec_can_come_before_due() {
+        let todo = import("Water rec:+1w due:2023-05-17 plants\nPay rent rec:+1m due:2023-05-14\nRun rec:+2w");
+        assert_eq!(todo.todo_items[0].title, "Water plants");
+        assert_eq!(todo.todo_items[0].recurrence, Some(Recurrence::Weekly(vec![Weekday::Wed])));
+        assert_eq!(todo.todo_items[1].recurrence, Some(Recurrence::Monthly(14)));
+        //no repeat matches it, so it stays in the title
+        assert_eq!(todo.todo_items[2].title, "Run rec:+2w");
+        assert_eq!(todo.todo_items[2].recurrence, None);
+    }
+
+    #[test]
+    fn unreadable_lines_are_skipped() {
+        let contents = "Good one\n\n2023-02-30 Bad date\nx 2023-05-01 due:soon\n+work @home\n";
+        let (items, skipped) = from_todo_txt(contents, at(2023, 5, 12, 17, 0));
+        assert_eq!(items.len(), 1);
+        let numbers: Vec<usize> = skipped.iter().map(|line| line.number).collect();
+        assert_eq!(numbers, [3, 4, 5]);
+        assert_eq!(skipped[0].line, "2023-02-30 Bad date");
+    }
+
+    #[test]
+    fn open_tasks_have_one_date() {
+        let todo = import("2024-01-01 2024-02-02 call Bob\n(A) 2023-05-01 2023-04-01 Two dates\nx 2023-05-02 2023-05-01 Done");
+        assert_eq!(todo.todo_items[0].created_at, Some(at(2024, 1, 1, 0, 0)));
+        assert_eq!(todo.todo_items[0].title, "2024-02-02 call Bob");
+        assert_eq!(todo.todo_items[1].title, "2023-04-01 Two dates");
+        let done = &todo.completed_items[0];
+        assert_eq!((done.completed_at, done.created_at), (Some(at(2023, 5, 2, 0, 0)), Some(at(2023, 5, 1, 0, 0))));
+        assert_eq!(done.title, "Done");
+    }
+}
@@ -1,19 +1,115 @@
-use home::home_dir;
+use crate::config_handler::config_handler::{config_path, load_config, Config};
 use crate::data_handler::data_handler::*;
+use crate::todo_backend::todo::{Archive, History, TodoError, TodoList, TodoResult};
+use std::env;
+use std::path::{Path, PathBuf};
+mod cli_handler;
+mod config_handler;
 mod data_handler;
+mod output_handler;
 mod todo_backend;
 mod tui_handler;
 mod parsing_handler;
 
 fn main() {
-    // let parsed = parsing_handler::parse("").unwrap();
-    // println!("{:?}", parsed);
-    let mut file = home_dir().expect("Could not find home directory");       
-    file.push(".todo_items");
-    //error handling the load file
-    let mut list = load_todo_list(&file).unwrap();
-    tui_handler::tui_handler::run_tui(&mut list).unwrap();
-
-    //For testing purposes not saving currently, uncomment to enable saving list
-    save_todo_list(&list, file).unwrap();
+    if let Err(e) = run() {
+        eprintln!("{e}");
+        std::process::exit(1);
+    }
+}
+
+fn run() -> TodoResult<()> {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    //--file or --list win over TODO_FILE, without any of them the default list is used
+    let file_flag = take_option(&mut args, "--file")?;
+    let list_flag = take_option(&mut args, "--list")?;
+    let mut file = match (file_flag, list_flag, env::var_os("TODO_FILE")) {
+        (Some(file), Some(name), _) => {
+            return Err(TodoError::invalid_input(&format!("--file {file} --list {name}"), "Give either --file or --list"))
+        }
+        (Some(file), None, _) => PathBuf::from(file),
+        (None, Some(name), _) => list_path(&name)?,
+        (None, None, Some(file)) => PathBuf::from(file),
+        (None, None, None) => list_path(DEFAULT_LIST)?,
+    };
+
+    let mut config = load_config(&config_path())?;
+    //TODO_UNDO_DEPTH and TODO_UNDO_PERSIST=1 win over undo_depth and persist_undo in the config
+    if let Ok(depth) = env::var("TODO_UNDO_DEPTH") {
+        config.undo_depth = match depth.parse::<usize>() {
+            Ok(r) => r,
+            Err(_) => return Err(TodoError::invalid_input(&depth, "TODO_UNDO_DEPTH should be a number")),
+        };
+    }
+    if let Ok(persist) = env::var("TODO_UNDO_PERSIST") {
+        config.persist_undo = persist == "1" || persist == "true";
+    }
+
+    //the TUI only opens when no command is given, ex: todo add "Write report" --due tomorrow.
+    //Picking another list in the TUI saves this one and opens the TUI again with the other one
+    loop {
+        let store = Store::new(file.clone(), config.backups, config.persist_undo);
+        let switch_to = match args.is_empty() {
+            true => {
+                let (mut list, mut archive, mut history) = load(&store, &config)?;
+                let current = list_name(&file);
+                let lists = list_names()?;
+                let switch_to = tui_handler::tui_handler::run_tui(&mut list, &mut archive, &mut history, &current, lists, &config, &store)?;
+                store.save(&list, &archive, &history)?;
+                switch_to
+            }
+            //a command holds the lock from loading to saving, so commands run at the same time take turns
+            false => {
+                store.locked(|| {
+                    let (mut list, mut archive, mut history) = load(&store, &config)?;
+                    cli_handler::cli_handler::run_cli(&args, &file, &mut list, &mut archive, &mut history, &config)?;
+                    return store.save(&list, &archive, &history);
+                })?;
+                None
+            }
+        };
+
+        match switch_to {
+            Some(name) => file = list_path(&name)?,
+            None => return Ok(()),
+        }
+    }
+}
+
+///Loads the list, its archive and the undo history if it is kept, old completed items get archived
+fn load(store: &Store, config: &Config) -> TodoResult<(TodoList, Archive, History)> {
+    let (mut list, mut archive) = store.load()?;
+    list.archive_completed(config.archive_days, &mut archive);
+    let history = match config.persist_undo {
+        true => load_history(&history_path(&store.file), config.undo_depth)?,
+        false => History::new(config.undo_depth),
+    };
+    return Ok((list, archive, history));
+}
+
+///Removes "--name value" or "--name=value" from the arguments and returns the value
+fn take_option(args: &mut Vec<String>, name: &str) -> TodoResult<Option<String>> {
+    let prefix = format!("{name}=");
+    let index = match args.iter().position(|arg| arg == name || arg.starts_with(&prefix)) {
+        Some(r) => r,
+        None => return Ok(None),
+    };
+    let arg = args.remove(index);
+    if let Some(value) = arg.strip_prefix(&prefix) {
+        return Ok(Some(value.to_owned()));
+    }
+    if index >= args.len() {
+        return Err(TodoError::parse(&arg, arg.len(), "a value"));
+    }
+    return Ok(Some(args.remove(index)));
+}
+
+///Lists in the data directory go by their name, other files by their file name
+fn list_name(file: &Path) -> String {
+    let in_data_dir = file.parent().is_some_and(|parent| parent == data_dir());
+    return match in_data_dir {
+        true => file.file_stem().unwrap_or_default().to_string_lossy().into_owned(),
+        false if list_path(DEFAULT_LIST).is_ok_and(|default| default == file) => DEFAULT_LIST.to_owned(),
+        false => file.display().to_string(),
+    };
 }
@@ -0,0 +1,233 @@
+pub mod output_handler {
+    use crate::todo_backend::todo::{TodoError, TodoItem, TodoResult};
+    use chrono::NaiveDateTime;
+    use serde::{Serialize, Serializer};
+    use std::io::Write;
+    use std::str::FromStr;
+
+    //dates in json, csv and tsv, plain uses the shorter "%Y-%m-%d %H:%M"
+    const MACHINE_DATE: &str = "%Y-%m-%dT%H:%M:%S";
+
+    const COLUMNS: [&str; 10] = [
+        "id", "parent", "status", "priority", "title", "due", "completed", "project", "tags", "repeat",
+    ];
+
+    ///How list output is printed, ex: todo list --format csv
+    #[derive(Clone, Copy, PartialEq, Default)]
+    pub enum OutputFormat {
+        //column aligned table for reading in the terminal
+        #[default]
+        Plain,
+        //one JSON object per line
+        Json,
+        Csv,
+        Tsv,
+    }
+
+    impl FromStr for OutputFormat {
+        type Err = TodoError;
+
+        fn from_str(s: &str) -> TodoResult<OutputFormat> {
+            match &*s.to_lowercase() {
+                "plain" | "table" => Ok(OutputFormat::Plain),
+                "json" | "jsonl" => Ok(OutputFormat::Json),
+                "csv" => Ok(OutputFormat::Csv),
+                "tsv" => Ok(OutputFormat::Tsv),
+                _ => Err(TodoError::invalid_input(s, "Expected plain, json, csv or tsv")),
+            }
+        }
+    }
+
+    ///One item with its position in the tree, subtasks get a row of their own below their parent
+    #[derive(Serialize)]
+    struct Row<'a> {
+        id: usize,
+        parent: Option<usize>,
+        #[serde(skip)]
+        depth: usize,
+        status: &'static str,
+        priority: String,
+        title: &'a str,
+        #[serde(serialize_with = "machine_date")]
+        due: Option<NaiveDateTime>,
+        #[serde(serialize_with = "machine_date")]
+        completed: Option<NaiveDateTime>,
+        project: Option<&'a str>,
+        tags: &'a [String],
+        repeat: Option<String>,
+    }
+    impl Row<'_> {
+        ///The values in the order of COLUMNS, dates use the given format
+        fn fields(&self, date_format: &str) -> Vec<String> {
+            let date = |date: Option<NaiveDateTime>| {
+                date.map(|r| r.format(date_format).to_string()).unwrap_or_default()
+            };
+            return vec![
+                self.id.to_string(),
+                self.parent.map(|r| r.to_string()).unwrap_or_default(),
+                self.status.to_owned(),
+                self.priority.clone(),
+                self.title.to_owned(),
+                date(self.due),
+                date(self.completed),
+                self.project.unwrap_or_default().to_owned(),
+                self.tags.join(","),
+                self.repeat.clone().unwrap_or_default(),
+            ];
+        }
+    }
+
+    fn machine_date<S: Serializer>(date: &Option<NaiveDateTime>, serializer: S) -> Result<S::Ok, S::Error> {
+        return match date {
+            Some(date) => serializer.collect_str(&date.format(MACHINE_DATE)),
+            None => serializer.serialize_none(),
+        };
+    }
+
+    fn collect_rows<'a>(item: &'a TodoItem, parent: Option<usize>, depth: usize, rows: &mut Vec<Row<'a>>) {
+        rows.push(Row {
+            id: item.id,
+            parent,
+            depth,
+            status: if item.completed { "done" } else { "todo" },
+            priority: item.priority.to_string(),
+            title: &item.title,
+            due: item.due_date,
+            completed: item.completed_at.filter(|_| item.completed),
+            project: item.project.as_deref(),
+            tags: &item.tags,
+            repeat: item.recurrence.as_ref().map(|r| r.to_string()),
+        });
+        item.children
+            .iter()
+            .for_each(|child| collect_rows(child, Some(item.id), depth + 1, rows));
+    }
+
+    ///Writes the items and all of their subtasks in the given format.
+    ///Every format has the same columns, csv and tsv start with a header line
+    pub fn write_items<'a>(
+        out: &mut impl Write,
+        items: impl Iterator<Item = &'a TodoItem>,
+        format: OutputFormat,
+    ) -> TodoResult<()> {
+        let mut rows = Vec::new();
+        items.for_each(|item| collect_rows(item, None, 0, &mut rows));
+
+        match format {
+            OutputFormat::Plain => write_table(out, &rows)?,
+            OutputFormat::Json => {
+                for row in &rows {
+                    serde_json::to_writer(&mut *out, row).map_err(TodoError::Serialization)?;
+                    writeln!(out).map_err(TodoError::Io)?;
+                }
+            }
+            OutputFormat::Csv => write_separated(out, &rows, ',', csv_field)?,
+            OutputFormat::Tsv => write_separated(out, &rows, '\t', tsv_field)?,
+        }
+        return Ok(());
+    }
+
+    fn write_separated(
+        out: &mut impl Write,
+        rows: &[Row],
+        separator: char,
+        escape: fn(&str) -> String,
+    ) -> TodoResult<()> {
+        let mut lines = vec![COLUMNS.join(&separator.to_string())];
+        for row in rows {
+            let fields: Vec<String> = row
+                .fields(MACHINE_DATE)
+                .iter()
+                .map(|field| escape(field))
+                .collect();
+            lines.push(fields.join(&separator.to_string()));
+        }
+        lines
+            .iter()
+            .try_for_each(|line| writeln!(out, "{line}"))
+            .map_err(TodoError::Io)?;
+        return Ok(());
+    }
+
+    ///Quotes fields with separators, quotes or line breaks in them, like RFC 4180 asks for
+    fn csv_field(field: &str) -> String {
+        if field.contains([',', '"', '\n', '\r']) {
+            return format!("\"{}\"", field.replace('"', "\"\""));
+        }
+        return field.to_owned();
+    }
+
+    //tsv has no quoting, so tabs and line breaks become spaces
+    fn tsv_field(field: &str) -> String {
+        return field.replace(['\t', '\n', '\r'], " ");
+    }
+
+    ///Pads every column to its widest value, subtask titles are indented below their parent
+    fn write_table(out: &mut impl Write, rows: &[Row]) -> TodoResult<()> {
+        let header: Vec<String> = COLUMNS.iter().map(|column| column.to_uppercase()).collect();
+        let mut lines = vec![header];
+        for row in rows {
+            let mut fields = row.fields("%Y-%m-%d %H:%M");
+            fields[4] = format!("{}{}", "  ".repeat(row.depth), fields[4]);
+            lines.push(fields);
+        }
+
+        let mut widths = vec![0; COLUMNS.len()];
+        for fields in &lines {
+            for (width, field) in widths.iter_mut().zip(fields) {
+                *width = (*width).max(field.chars().count());
+            }
+        }
+        for fields in &lines {
+            let line: Vec<String> = fields
+                .iter()
+                .zip(&widths)
+                .map(|(field, width)| format!("{field:width$}"))
+                .collect();
+            writeln!(out, "{}", line.join("  ").trim_end()).map_err(TodoError::Io)?;
+        }
+        return Ok(());
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::{csv_field, tsv_field, write_items, OutputFormat};
+        use crate::todo_backend::todo::TodoItem;
+
+        #[test]
+        fn csv_quotes_fields_that_need_it() {
+            assert_eq!(csv_field("plain title"), "plain title");
+            assert_eq!(csv_field("milk, eggs"), "\"milk, eggs\"");
+            assert_eq!(csv_field("the \"big\" one"), "\"the \"\"big\"\" one\"");
+            assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+            assert_eq!(csv_field("crlf\r\n"), "\"crlf\r\n\"");
+            //tabs need no quoting in csv
+            assert_eq!(csv_field("a\tb"), "a\tb");
+        }
+
+        #[test]
+        fn tsv_replaces_tabs_and_line_breaks() {
+            assert_eq!(tsv_field("a\tb"), "a b");
+            assert_eq!(tsv_field("two\r\nlines"), "two  lines");
+            assert_eq!(tsv_field("milk, \"eggs\""), "milk, \"eggs\"");
+        }
+
+        #[test]
+        fn rows_escape_every_field() {
+            let mut item = TodoItem::new(1, String::new(), None);
+            item.title = "milk, \"eggs\"\nand\tbread".to_owned();
+            item.tags = vec!["shop".to_owned(), "home".to_owned()];
+            let mut csv = Vec::new();
+            write_items(&mut csv, [&item].into_iter(), OutputFormat::Csv).unwrap();
+            let csv = String::from_utf8(csv).unwrap();
+            assert!(csv.ends_with("\n1,,todo,none,\"milk, \"\"eggs\"\"\nand\tbread\",,,,\"shop,home\",\n"), "{csv}");
+
+            let mut tsv = Vec::new();
+            write_items(&mut tsv, [&item].into_iter(), OutputFormat::Tsv).unwrap();
+            let tsv = String::from_utf8(tsv).unwrap();
+            //the header and one row
+            assert_eq!(tsv.lines().count(), 2);
+            assert!(tsv.ends_with("\n1\t\ttodo\tnone\tmilk, \"eggs\" and bread\t\t\t\tshop,home\t\n"), "{tsv}");
+        }
+    }
+}
@@ -1,68 +1,793 @@
-use std::{
-    io::{self, ErrorKind},
-    collections::HashMap
-};
 use phf::phf_map;
-use crate::tui_handler::tui_handler::{State, AddState};
+use chrono::NaiveDateTime;
+use crate::todo_backend::todo::{
+    flatten, parse_future_date, Archive, Filter, History, Priority, Recurrence, Shelf, TodoError,
+    TodoList, TodoResult, DEFAULT_ARCHIVE_DAYS,
+};
+use crate::tui_handler::tui_handler::{State, AddState, ItemPrompt};
+
+//ranges bigger than this are most likely a typo
+const MAX_RANGE: usize = 1000;
 
 #[derive(Clone, Copy, Debug)]
 pub enum UserCommand {
     AddTask,
     AddTaskDate,
     CompleteTask,
+    CompleteWithSubtasks,
+    AddSubtask,
+    Indent,
+    Outdent,
+    ToggleCollapse,
     UncompleteTask,
+    SetPriority,
+    SetRecurrence,
+    Edit,
+    Filter,
+    ShowArchive,
+    ArchiveCompleted,
+    RestoreArchived,
+    Delete,
+    ShowTrash,
+    RestoreDeleted,
+    EmptyTrash,
+    Undo,
+    Redo,
+    ShowKeys,
     Quit,
 }
 
 #[derive(Debug)]
 pub struct Command {
     command: UserCommand,
-    index: Option<usize>,
+    input: String,
+    arguments: Vec<Argument>,
+}
+
+#[derive(Debug)]
+pub enum Token {
+    Word(String),
+    //a fully quoted argument, never read as an id or a key:value pair
+    Quoted(String),
+    //key:value, only for the keys in KEYS so titles like "Call at 10:30" stay words
+    Pair(String, String),
+}
+
+#[derive(Debug)]
+pub struct Argument {
+    //character position the argument starts at, used for error messages
+    position: usize,
+    token: Token,
 }
 
 static MAPPED_COMMANDS: phf::Map<&'static str, UserCommand> = phf_map! {
     "AddTask" => UserCommand::AddTask,
     "AddTaskDate" => UserCommand::AddTaskDate,
-    "CompleteTask" => UserCommand::CompleteTask, 
+    "CompleteTask" => UserCommand::CompleteTask,
+    "CompleteWithSubtasks" => UserCommand::CompleteWithSubtasks,
+    "AddSubtask" => UserCommand::AddSubtask,
+    "Indent" => UserCommand::Indent,
+    "Outdent" => UserCommand::Outdent,
+    "ToggleCollapse" => UserCommand::ToggleCollapse,
     "UncompleteTask" => UserCommand::UncompleteTask,
+    "SetPriority" => UserCommand::SetPriority,
+    "SetRecurrence" => UserCommand::SetRecurrence,
+    "Edit" => UserCommand::Edit,
+    "Filter" => UserCommand::Filter,
+    "ShowArchive" => UserCommand::ShowArchive,
+    "ArchiveCompleted" => UserCommand::ArchiveCompleted,
+    "RestoreArchived" => UserCommand::RestoreArchived,
+    "Delete" => UserCommand::Delete,
+    "ShowTrash" => UserCommand::ShowTrash,
+    "RestoreDeleted" => UserCommand::RestoreDeleted,
+    "EmptyTrash" => UserCommand::EmptyTrash,
+    "Undo" => UserCommand::Undo,
+    "Redo" => UserCommand::Redo,
+    "ShowKeys" => UserCommand::ShowKeys,
     "Quit" => UserCommand::Quit,
+    //short names
+    "add" => UserCommand::AddTask,
+    "adddate" => UserCommand::AddTaskDate,
+    "done" => UserCommand::CompleteTask,
+    "doneall" => UserCommand::CompleteWithSubtasks,
+    "sub" => UserCommand::AddSubtask,
+    "indent" => UserCommand::Indent,
+    "outdent" => UserCommand::Outdent,
+    "toggle" => UserCommand::ToggleCollapse,
+    "undone" => UserCommand::UncompleteTask,
+    "prio" => UserCommand::SetPriority,
+    "repeat" => UserCommand::SetRecurrence,
+    "edit" => UserCommand::Edit,
+    "filter" => UserCommand::Filter,
+    "showarchive" => UserCommand::ShowArchive,
+    "archive" => UserCommand::ArchiveCompleted,
+    "restore" => UserCommand::RestoreArchived,
+    "rm" => UserCommand::Delete,
+    "delete" => UserCommand::Delete,
+    "showtrash" => UserCommand::ShowTrash,
+    "undelete" => UserCommand::RestoreDeleted,
+    "emptytrash" => UserCommand::EmptyTrash,
+    "undo" => UserCommand::Undo,
+    "redo" => UserCommand::Redo,
+    "keys" => UserCommand::ShowKeys,
+    "quit" => UserCommand::Quit,
+    "q" => UserCommand::Quit,
 };
 
-pub fn parse(user_input: &str) -> io::Result<Command> {
-    let tokens = tokenize(user_input);
-    let command = match MAPPED_COMMANDS.get(tokens[0]) {
-        Some(r) => r,
-        None => return Err(ErrorKind::NotFound.into())
-    };
-    
-    let index; 
-    if tokens.len() > 1 {
-        let parsed_num = match tokens[1].parse::<usize>(){
-            Ok(r) => r,
-            Err(_) => return Err(ErrorKind::InvalidInput.into())
+const KEYS: [&str; 10] = ["title", "due", "prio", "priority", "every", "repeat", "project", "tags", "parent", "older"];
+
+pub fn parse(user_input: &str) -> TodoResult<Command> {
+    let arguments = tokenize(user_input)?;
+    return into_command(user_input.to_owned(), arguments);
+}
+
+///Builds a command from arguments that were already split by the shell,
+///ex: ["add", "Write report", "--due", "next fri"]. "--key value", "--key=value"
+///and "key:value" all become key:value pairs
+pub fn parse_args(args: &[String]) -> TodoResult<Command> {
+    let input = args.join(" ");
+    let mut arguments = Vec::new();
+    let mut position = 0;
+    let mut args_iter = args.iter();
+    while let Some(arg) = args_iter.next() {
+        let arg_position = position;
+        position += arg.chars().count() + 1;
+
+        let token = match arg.strip_prefix("--") {
+            Some(flag) => {
+                let (key, value) = match flag.split_once('=') {
+                    Some((key, value)) => (key, value.to_owned()),
+                    None => match args_iter.next() {
+                        Some(value) => {
+                            position += value.chars().count() + 1;
+                            (flag, value.to_owned())
+                        }
+                        None => return Err(TodoError::parse(&input, position, "a value")),
+                    },
+                };
+                if !KEYS.contains(&key) {
+                    return Err(TodoError::parse(&input, arg_position, "a known option"));
+                }
+                Token::Pair(key.to_owned(), value)
+            }
+            None => match arg.split_once(':') {
+                Some((key, value)) if KEYS.contains(&key) => Token::Pair(key.to_owned(), value.to_owned()),
+                _ => Token::Word(arg.to_owned()),
+            },
         };
-        index = Some(parsed_num); 
-    }
-    else {
-        index = None;
+        arguments.push(Argument{position: arg_position, token});
     }
+    return into_command(input, arguments);
+}
 
-    let command = Command{command: command.clone(), index};
-    return Ok(command);
+///The first argument names the command, the rest are its arguments
+fn into_command(input: String, mut arguments: Vec<Argument>) -> TodoResult<Command> {
+    if arguments.is_empty() {
+        return Err(TodoError::parse(&input, 0, "a command"));
+    }
+    let name = match arguments.remove(0).token {
+        Token::Word(name) => name,
+        _ => return Err(TodoError::parse(&input, 0, "a command")),
+    };
+    let command = match MAPPED_COMMANDS.get(&*name) {
+        Some(r) => *r,
+        None => return Err(TodoError::UnknownCommand(name)),
+    };
+    return Ok(Command{command, input, arguments});
 }
 
-fn tokenize(user_input: &str) -> Vec<&str> {
-    let tokens: Vec<&str> = user_input.split(" ").collect();
-    return tokens;
-} 
+///Splits the input into arguments, anything between double or single quotes stays
+///together (\ escapes the next character inside quotes), ex: due:"next fri" "Write report"
+fn tokenize(user_input: &str) -> TodoResult<Vec<Argument>> {
+    let mut arguments = Vec::new();
+    let mut chars = user_input.chars().enumerate().peekable();
+    while let Some(&(position, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        let mut text = String::new();
+        let mut key_end = None;
+        let (mut quoted, mut unquoted) = (false, false);
+        while let Some(&(_, c)) = chars.peek() {
+            if c.is_whitespace() {
+                break;
+            }
+            chars.next();
+            //quotes only open at the start of an argument or right after a key, so "don't" is a word
+            let opens_quote = text.is_empty() || key_end.is_some_and(|end| end + 1 == text.len());
+            match c {
+                '"' | '\'' if opens_quote && !quoted => {
+                    quoted = true;
+                    loop {
+                        match chars.next() {
+                            Some((_, '\\')) => match chars.next() {
+                                Some((_, escaped)) => text.push(escaped),
+                                None => break,
+                            },
+                            Some((_, closing)) if closing == c => break,
+                            Some((_, other)) => text.push(other),
+                            None => {
+                                let end = user_input.chars().count();
+                                return Err(TodoError::parse(user_input, end, "a closing quote"));
+                            }
+                        }
+                    }
+                }
+                ':' if key_end.is_none() && !quoted => {
+                    key_end = Some(text.len());
+                    text.push(c);
+                    unquoted = true;
+                }
+                c => {
+                    text.push(c);
+                    unquoted = true;
+                }
+            }
+        }
+
+        let token = match key_end {
+            Some(end) if KEYS.contains(&&text[..end]) => {
+                Token::Pair(text[..end].to_owned(), text[end + 1..].to_owned())
+            }
+            _ if quoted && !unquoted => Token::Quoted(text),
+            _ => Token::Word(text),
+        };
+        arguments.push(Argument{position, token});
+    }
+    return Ok(arguments);
+}
 
-pub fn handle_command(cmd: Command) -> io::Result<State> {
+///Commands given arguments are applied straight away, otherwise the TUI asks for the rest
+pub fn handle_command(
+    cmd: Command,
+    todo: &mut TodoList,
+    archive: &mut Archive,
+    filter: &mut Filter,
+    history: &mut History,
+) -> TodoResult<State> {
     use UserCommand::*;
+    let input = &*cmd.input;
+    let arguments = &*cmd.arguments;
+    //optionally followed by how many changes to undo or redo, ex: "undo 3"
+    if let Undo | Redo = cmd.command {
+        let times = match arguments {
+            [] => 1,
+            [Argument{token: Token::Word(times), position}] => match times.parse::<usize>() {
+                Ok(r) => r,
+                Err(_) => return Err(TodoError::parse(input, *position, "a number")),
+            },
+            [other] => return Err(TodoError::parse(input, other.position, "a number")),
+            [_, extra, ..] => return Err(TodoError::parse(input, extra.position, "the end of the command")),
+        };
+        match cmd.command {
+            Undo => history.undo(todo, archive, times)?,
+            _ => history.redo(todo, archive, times)?,
+        }
+        return Ok(State::Viewing);
+    }
+    if arguments.is_empty() {
+        return prompt_for(cmd.command, todo, archive, input);
+    }
+
     match cmd.command {
+        AddTask | AddTaskDate => {
+            add_from_arguments(input, arguments, None, todo)?;
+        }
+        AddSubtask => {
+            let parent_id = parse_ids(input, &arguments[..1])?;
+            if parent_id.len() != 1 {
+                return Err(TodoError::parse(input, arguments[0].position, "a single id"));
+            }
+            add_from_arguments(input, &arguments[1..], Some(parent_id[0]), todo)?;
+        }
+        CompleteTask => {
+            let ids = parse_ids(input, arguments)?;
+            check_ids(todo, &ids, Some(false))?;
+            for id in ids {
+                todo.complete_item(id, false)?;
+            }
+        }
+        CompleteWithSubtasks => apply_to_ids(ItemPrompt::CompleteWithSubtasks, input, arguments, todo)?,
+        Indent => apply_to_ids(ItemPrompt::Indent, input, arguments, todo)?,
+        Outdent => apply_to_ids(ItemPrompt::Outdent, input, arguments, todo)?,
+        ToggleCollapse => apply_to_ids(ItemPrompt::ToggleCollapse, input, arguments, todo)?,
+        UncompleteTask => {
+            let ids = parse_ids(input, arguments)?;
+            check_ids(todo, &ids, Some(true))?;
+            for id in ids {
+                todo.uncomplete_item(id)?;
+            }
+        }
+        //the priority comes last since it can be a number too, ex: "prio 3 4 high"
+        SetPriority => {
+            let (last, ids) = arguments.split_last().unwrap();
+            //"prio 3" is missing the priority, "prio high" the id
+            if ids.is_empty() {
+                return match leading_ids(arguments) {
+                    0 => Err(TodoError::parse(input, last.position, "an id")),
+                    _ => Err(TodoError::parse(input, input.chars().count(), "a priority")),
+                };
+            }
+            let ids = parse_ids(input, ids)?;
+            let priority = text_of(last).parse::<Priority>()?;
+            check_ids(todo, &ids, None)?;
+            for id in ids {
+                todo.set_priority(id, priority)?;
+            }
+        }
+        SetRecurrence => {
+            let (ids, rule) = arguments.split_at(leading_ids(arguments));
+            let rule = join_words(input, rule, "a rule")?;
+            let recurrence = match &*rule {
+                "none" => None,
+                rule => Some(rule.parse::<Recurrence>()?),
+            };
+            let ids = parse_ids(input, ids)?;
+            if ids.is_empty() {
+                return Err(TodoError::parse(input, arguments[0].position, "an id"));
+            }
+            check_ids(todo, &ids, None)?;
+            for id in ids {
+                todo.set_recurrence(id, recurrence.clone())?;
+            }
+        }
+        Edit => {
+            let (ids, pairs) = arguments.split_at(leading_ids(arguments));
+            let ids = parse_ids(input, ids)?;
+            if ids.is_empty() {
+                return Err(TodoError::parse(input, arguments[0].position, "an id"));
+            }
+            //a single id without any changes opens the edit form
+            if let ([id], true) = (&*ids, pairs.is_empty()) {
+                check_ids(todo, &ids, None)?;
+                return Ok(State::EditingTodo(*id));
+            }
+            let changes = Changes::parse(input, pairs)?;
+            if changes.parent.is_some() {
+                return Err(TodoError::invalid_input(input, "Use indent or outdent to move an item"));
+            }
+            check_ids(todo, &ids, None)?;
+            for id in ids {
+                changes.apply(todo, id)?;
+            }
+        }
+        //everything after the command is the filter, ex: "filter +backend @review"
+        Filter => {
+            *filter = input.trim_start()[name_length(input)..].parse()?;
+        }
+        ArchiveCompleted => {
+            let days = match (&arguments[0].token, arguments.get(1)) {
+                (Token::Word(days), None) => match days.parse::<u32>() {
+                    Ok(r) => i64::from(r),
+                    Err(_) => return Err(TodoError::parse(input, arguments[0].position, "a number of days")),
+                },
+                (_, Some(extra)) => return Err(TodoError::parse(input, extra.position, "the end of the command")),
+                _ => return Err(TodoError::parse(input, arguments[0].position, "a number of days")),
+            };
+            todo.archive_completed(days, archive);
+        }
+        RestoreArchived | RestoreDeleted => {
+            let shelf = match cmd.command {
+                RestoreArchived => Shelf::Archive,
+                _ => Shelf::Trash,
+            };
+            let ids = parse_ids(input, arguments)?;
+            if let Some(id) = ids.iter().find(|id| !archive.shelf(shelf).iter().any(|item| item.id == **id)) {
+                return Err(TodoError::UnknownId(*id));
+            }
+            for id in ids {
+                match shelf {
+                    Shelf::Archive => todo.restore_archived(id, archive)?,
+                    Shelf::Trash => todo.restore_deleted(id, archive)?,
+                }
+            }
+            return Ok(State::ViewingArchive(shelf));
+        }
+        //nothing is deleted until the TUI gets a yes
+        Delete => return Ok(State::ConfirmingDelete(select_for_delete(input, arguments, todo)?)),
+        ShowArchive | ShowTrash | EmptyTrash | ShowKeys | Quit | Undo | Redo => {
+            return Err(TodoError::parse(input, arguments[0].position, "the end of the command"));
+        }
+    }
+    return Ok(State::Viewing);
+}
+
+///What a command without arguments does, most of them ask for the rest in the buffer
+fn prompt_for(command: UserCommand, todo: &mut TodoList, archive: &mut Archive, input: &str) -> TodoResult<State> {
+    use UserCommand::*;
+    return match command {
+        CompleteWithSubtasks => Ok(State::EnteringItemId(ItemPrompt::CompleteWithSubtasks)),
+        Indent => Ok(State::EnteringItemId(ItemPrompt::Indent)),
+        Outdent => Ok(State::EnteringItemId(ItemPrompt::Outdent)),
+        ToggleCollapse => Ok(State::EnteringItemId(ItemPrompt::ToggleCollapse)),
+        AddSubtask => Ok(State::AddingSubtask),
+        ArchiveCompleted => {
+            todo.archive_completed(DEFAULT_ARCHIVE_DAYS, archive);
+            Ok(State::Viewing)
+        }
+        ShowArchive => Ok(State::ViewingArchive(Shelf::Archive)),
+        RestoreArchived => Ok(State::RestoringArchived(Shelf::Archive)),
+        ShowTrash => Ok(State::ViewingArchive(Shelf::Trash)),
+        RestoreDeleted => Ok(State::RestoringArchived(Shelf::Trash)),
+        EmptyTrash => {
+            archive.empty_trash();
+            Ok(State::Viewing)
+        }
+        Delete => Err(TodoError::parse(input, input.chars().count(), "ids or what to delete")),
         AddTask => Ok(State::AddingTodo),
         AddTaskDate => Ok(State::AddingTodoDate(AddState::EnteringName)),
         CompleteTask => Ok(State::CompletingTodo),
         UncompleteTask => Ok(State::UncompletingTodo),
+        SetPriority => Ok(State::SettingPriority),
+        SetRecurrence => Ok(State::SettingRecurrence),
+        Edit => Err(TodoError::parse(input, input.chars().count(), "an id")),
+        Filter => Ok(State::Filtering),
+        Undo | Redo => Ok(State::Viewing),
+        ShowKeys => Ok(State::ViewingKeys),
         Quit => Ok(State::Quitting),
+    };
+}
+
+///Adds an item from the words (the title) and key:value pairs of a command,
+///ex: add "Write report" due:fri prio:high +work
+fn add_from_arguments(
+    input: &str,
+    arguments: &[Argument],
+    parent_id: Option<usize>,
+    todo: &mut TodoList,
+) -> TodoResult<usize> {
+    let (words, pairs): (Vec<&Argument>, Vec<&Argument>) = arguments
+        .iter()
+        .partition(|argument| !matches!(argument.token, Token::Pair(..)));
+    let mut changes = Changes::parse(input, pairs)?;
+    let title = match changes.title.take() {
+        Some(title) if words.is_empty() => title,
+        Some(_) => return Err(TodoError::invalid_input(input, "The title was given twice")),
+        None => join_words(input, words, "a title")?,
+    };
+
+    let parent_id = match (parent_id, changes.parent) {
+        (Some(_), Some(_)) => return Err(TodoError::invalid_input(input, "The parent was given twice")),
+        (id, parent) => id.or(parent),
+    };
+    let id = match parent_id {
+        Some(parent_id) => todo.add_subtask(parent_id, &title)?,
+        None => todo.add_item(&title)?,
+    };
+    changes.apply(todo, id)?;
+    return Ok(id);
+}
+
+///Fields given as key:value pairs, everything is parsed before the list is changed.
+///The outer Option is whether the field was given, "none" clears optional fields
+#[derive(Default)]
+struct Changes {
+    title: Option<String>,
+    due_date: Option<Option<NaiveDateTime>>,
+    priority: Option<Priority>,
+    recurrence: Option<Option<Recurrence>>,
+    project: Option<Option<String>>,
+    tags: Option<Vec<String>>,
+    parent: Option<usize>,
+}
+
+impl Changes {
+    fn parse<'a>(input: &str, pairs: impl IntoIterator<Item = &'a Argument>) -> TodoResult<Changes> {
+        let mut changes = Changes::default();
+        for argument in pairs {
+            let (key, value) = match &argument.token {
+                Token::Pair(key, value) => (&**key, value.trim()),
+                _ => return Err(TodoError::parse(input, argument.position, "a key:value pair")),
+            };
+            if value.is_empty() {
+                return Err(TodoError::parse(input, argument.position + key.len() + 1, "a value"));
+            }
+            let cleared = value == "none";
+            match key {
+                "title" => changes.title = Some(value.to_owned()),
+                "due" if cleared => changes.due_date = Some(None),
+                "due" => changes.due_date = Some(Some(parse_future_date(value)?)),
+                "prio" | "priority" => changes.priority = Some(value.parse::<Priority>()?),
+                "every" | "repeat" if cleared => changes.recurrence = Some(None),
+                "every" | "repeat" => changes.recurrence = Some(Some(value.parse::<Recurrence>()?)),
+                "project" if cleared => changes.project = Some(None),
+                "project" => changes.project = Some(Some(value.trim_start_matches('+').to_owned())),
+                //comma separated, ex: tags:review,urgent
+                "tags" if cleared => changes.tags = Some(Vec::new()),
+                "tags" => {
+                    let mut tags: Vec<String> = Vec::new();
+                    for tag in value.split(',').map(|tag| tag.trim().trim_start_matches('@')) {
+                        if !tag.is_empty() && !tags.iter().any(|t| t == tag) {
+                            tags.push(tag.to_owned());
+                        }
+                    }
+                    changes.tags = Some(tags);
+                }
+                "parent" => match value.parse::<usize>() {
+                    Ok(r) => changes.parent = Some(r),
+                    Err(_) => return Err(TodoError::parse(input, argument.position + key.len() + 1, "an id")),
+                },
+                _ => return Err(TodoError::parse(input, argument.position, "a known key")),
+            }
+        }
+        return Ok(changes);
+    }
+
+    fn apply(&self, todo: &mut TodoList, item_id: usize) -> TodoResult<()> {
+        if let Some(title) = &self.title {
+            todo.set_title(item_id, title)?;
+        }
+        if let Some(due_date) = self.due_date {
+            todo.set_due_date(item_id, due_date)?;
+        }
+        if let Some(priority) = self.priority {
+            todo.set_priority(item_id, priority)?;
+        }
+        if let Some(recurrence) = &self.recurrence {
+            todo.set_recurrence(item_id, recurrence.clone())?;
+        }
+        if let Some(project) = &self.project {
+            todo.set_project(item_id, project.clone())?;
+        }
+        if let Some(tags) = &self.tags {
+            todo.set_tags(item_id, tags.clone())?;
+        }
+        return Ok(());
+    }
+}
+
+///The ids rm works on, either given directly ("rm 4 7-9") or picked by what the items are,
+///ex: "rm completed older:30" or "rm +work @old". Picked items have to match everything given
+pub fn select_for_delete(input: &str, arguments: &[Argument], todo: &mut TodoList) -> TodoResult<Vec<usize>> {
+    if leading_ids(arguments) == arguments.len() {
+        let ids = parse_ids(input, arguments)?;
+        check_ids(todo, &ids, None)?;
+        return Ok(ids);
+    }
+
+    let mut completed = None;
+    let mut older = None;
+    let mut filter_words = Vec::new();
+    for argument in arguments {
+        match &argument.token {
+            Token::Word(word) if word == "completed" => completed = Some(true),
+            Token::Word(word) if word == "todo" => completed = Some(false),
+            Token::Word(word) if word.starts_with(['+', '@']) => filter_words.push(word.as_str()),
+            //days since the item was completed
+            Token::Pair(key, days) if key == "older" => match days.parse::<i64>() {
+                Ok(r) => older = Some(r),
+                Err(_) => return Err(TodoError::parse(input, argument.position + key.len() + 1, "a number of days")),
+            },
+            _ => {
+                let expected = "an id, completed, todo, +project, @tag or older:days";
+                return Err(TodoError::parse(input, argument.position, expected));
+            }
+        }
+    }
+    let filter = filter_words.join(" ").parse::<Filter>()?;
+    let now = chrono::offset::Local::now().naive_local();
+    let cutoff = older.map(|days| now - chrono::Duration::days(days));
+
+    let ids: Vec<usize> = flatten(&todo.todo_items)
+        .into_iter()
+        .chain(flatten(&todo.completed_items))
+        .filter(|item| completed.is_none_or(|completed| item.completed == completed))
+        .filter(|item| filter.matches(item))
+        .filter(|item| cutoff.is_none_or(|cutoff| matches!(item.completed_at, Some(done) if done < cutoff)))
+        .map(|item| item.id)
+        .collect();
+    if ids.is_empty() {
+        return Err(TodoError::invalid_input(input, "No items match"));
+    }
+    return Ok(ids);
+}
+
+fn apply_to_ids(prompt: ItemPrompt, input: &str, arguments: &[Argument], todo: &mut TodoList) -> TodoResult<()> {
+    let ids = parse_ids(input, arguments)?;
+    check_ids(todo, &ids, None)?;
+    for id in ids {
+        prompt.apply(todo, id)?;
+    }
+    return Ok(());
+}
+
+///Reads ids given as "4 7 9", "4,7,9", "7-9" or any mix of them
+fn parse_ids(input: &str, arguments: &[Argument]) -> TodoResult<Vec<usize>> {
+    let mut ids = Vec::new();
+    for argument in arguments {
+        let word = match &argument.token {
+            Token::Word(word) => word,
+            _ => return Err(TodoError::parse(input, argument.position, "an id")),
+        };
+        for part in word.split(',').filter(|part| !part.is_empty()) {
+            let parse_id = |id: &str| match id.parse::<usize>() {
+                Ok(r) => Ok(r),
+                Err(_) => Err(TodoError::parse(input, argument.position, "an id")),
+            };
+            match part.split_once('-') {
+                Some((start, end)) => {
+                    let (start, end) = (parse_id(start)?, parse_id(end)?);
+                    if start > end || end - start >= MAX_RANGE {
+                        return Err(TodoError::invalid_input(part, "Invalid range of ids"));
+                    }
+                    ids.extend(start..=end);
+                }
+                None => ids.push(parse_id(part)?),
+            }
+        }
+    }
+    let mut seen = Vec::new();
+    ids.retain(|id| match seen.contains(id) {
+        true => false,
+        false => {
+            seen.push(*id);
+            true
+        }
+    });
+    return Ok(ids);
+}
+
+///Amount of arguments at the start that look like ids
+fn leading_ids(arguments: &[Argument]) -> usize {
+    return arguments
+        .iter()
+        .take_while(|argument| match &argument.token {
+            Token::Word(word) => word.starts_with(|c: char| c.is_ascii_digit()),
+            _ => false,
+        })
+        .count();
+}
+
+///Makes sure every id exists before anything is changed, so one bad id leaves the list untouched.
+///completed also checks the items are (or aren't) completed yet
+fn check_ids(todo: &mut TodoList, ids: &[usize], completed: Option<bool>) -> TodoResult<()> {
+    for id in ids {
+        match (todo.find_item_mut(*id), completed) {
+            (None, _) => return Err(TodoError::UnknownId(*id)),
+            (Some(item), Some(false)) if item.completed => {
+                return Err(TodoError::invalid_input(&id.to_string(), "Item is already completed"));
+            }
+            (Some(item), Some(true)) if !item.completed => {
+                return Err(TodoError::invalid_input(&id.to_string(), "Item is not completed"));
+            }
+            _ => {}
+        }
+    }
+    return Ok(());
+}
+
+fn text_of(argument: &Argument) -> String {
+    return match &argument.token {
+        Token::Word(text) | Token::Quoted(text) => text.to_owned(),
+        Token::Pair(key, value) => format!("{key}:{value}"),
+    };
+}
+
+fn join_words<'a>(input: &str, words: impl IntoIterator<Item = &'a Argument>, expected: &str) -> TodoResult<String> {
+    let words: Vec<String> = words.into_iter().map(text_of).collect();
+    if words.is_empty() {
+        return Err(TodoError::parse(input, input.chars().count(), expected));
+    }
+    return Ok(words.join(" "));
+}
+
+///Length in bytes of the command name at the start of the (trimmed) input
+fn name_length(input: &str) -> usize {
+    let trimmed = input.trim_start();
+    return trimmed.find(char::is_whitespace).unwrap_or(trimmed.len());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{handle_command, parse, parse_ids, tokenize, Changes, Token};
+    use crate::todo_backend::todo::{Archive, Filter, History, Priority, TodoError, TodoList};
+
+    fn position_of(error: TodoError) -> usize {
+        return match error {
+            TodoError::Parse { position, .. } => position,
+            e => panic!("expected a parse error, got {e}"),
+        };
+    }
+
+    #[test]
+    fn tokenize_reads_words_quotes_and_pairs() {
+        let arguments = tokenize("add 'Buy milk' prio:high at 10:30 don't").unwrap();
+        let positions: Vec<usize> = arguments.iter().map(|argument| argument.position).collect();
+        assert_eq!(positions, [0, 4, 15, 25, 28, 34]);
+        assert!(matches!(&arguments[1].token, Token::Quoted(text) if text == "Buy milk"));
+        assert!(matches!(&arguments[2].token, Token::Pair(key, value) if key == "prio" && value == "high"));
+        //only known keys become pairs
+        assert!(matches!(&arguments[4].token, Token::Word(word) if word == "10:30"));
+        assert!(matches!(&arguments[5].token, Token::Word(word) if word == "don't"));
+    }
+
+    #[test]
+    fn tokenize_quotes_after_a_key() {
+        let arguments = tokenize(r#"title:"a \"b\" c""#).unwrap();
+        assert!(matches!(&arguments[0].token, Token::Pair(key, value) if key == "title" && value == r#"a "b" c"#));
+        assert_eq!(position_of(tokenize("add 'unclosed").unwrap_err()), 13);
+    }
+
+    #[test]
+    fn parse_ids_reads_lists_and_ranges() {
+        let input = "4 7-9 2,4,10";
+        let arguments = tokenize(input).unwrap();
+        assert_eq!(parse_ids(input, &arguments).unwrap(), [4, 7, 8, 9, 2, 10]);
+
+        let input = "4 x";
+        assert_eq!(position_of(parse_ids(input, &tokenize(input).unwrap()).unwrap_err()), 2);
+        let input = "9-7";
+        assert!(matches!(parse_ids(input, &tokenize(input).unwrap()), Err(TodoError::InvalidInput { .. })));
+    }
+
+    #[test]
+    fn changes_parse_reads_every_key() {
+        let input = "prio:high project:+work tags:@a,b,a parent:3 due:none";
+        let arguments = tokenize(input).unwrap();
+        let changes = Changes::parse(input, &arguments).unwrap();
+        assert_eq!(changes.priority, Some(Priority::High));
+        assert_eq!(changes.project, Some(Some("work".to_owned())));
+        assert_eq!(changes.tags, Some(vec!["a".to_owned(), "b".to_owned()]));
+        assert_eq!(changes.parent, Some(3));
+        assert_eq!(changes.due_date, Some(None));
+        assert_eq!(changes.title, None);
+    }
+
+    #[test]
+    fn changes_parse_points_at_the_bad_value() {
+        let input = "parent:x";
+        let arguments = tokenize(input).unwrap();
+        assert_eq!(position_of(Changes::parse(input, &arguments).err().unwrap()), 7);
+        let input = "title:";
+        let arguments = tokenize(input).unwrap();
+        assert_eq!(position_of(Changes::parse(input, &arguments).err().unwrap()), 6);
+    }
+
+    #[test]
+    fn edit_and_repeat_need_an_id() {
+        let mut todo = TodoList::new();
+        todo.add_item("Water plants").unwrap();
+        for input in ["edit title:foo", "repeat daily"] {
+            let result = handle_command(
+                parse(input).unwrap(),
+                &mut todo,
+                &mut Archive::default(),
+                &mut Filter::default(),
+                &mut History::new(10),
+            );
+            assert_eq!(position_of(result.err().unwrap()), input.find(' ').unwrap() + 1);
+        }
+        assert_eq!(todo.todo_items[0].title, "Water plants");
+    }
+
+    fn run(input: &str, todo: &mut TodoList) -> Result<(), TodoError> {
+        let command = parse(input)?;
+        return handle_command(command, todo, &mut Archive::default(), &mut Filter::default(), &mut History::new(10))
+            .map(|_| ());
+    }
+
+    #[test]
+    fn prio_reports_the_missing_part() {
+        let mut todo = TodoList::new();
+        todo.add_item("Water plants").unwrap();
+        assert_eq!(position_of(run("prio high", &mut todo).unwrap_err()), 5);
+        assert_eq!(position_of(run("prio high 1", &mut todo).unwrap_err()), 5);
+        assert_eq!(position_of(run("prio 1", &mut todo).unwrap_err()), 6);
+        run("prio 1 high", &mut todo).unwrap();
+        assert_eq!(todo.todo_items[0].priority, Priority::High);
+    }
+
+    #[test]
+    fn archive_needs_a_positive_number_of_days() {
+        let mut todo = TodoList::new();
+        let id = todo.add_item("Water plants").unwrap();
+        todo.complete_item(id, false).unwrap();
+        for input in ["archive -5", "archive x", "archive 3 4"] {
+            assert!(matches!(run(input, &mut todo), Err(TodoError::Parse { .. })), "{input}");
+        }
+        run("archive 0", &mut todo).unwrap();
+        assert!(todo.completed_items.is_empty());
     }
 }
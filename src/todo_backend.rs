@@ -1,131 +1,760 @@
+pub mod due_date;
+pub mod error;
+pub mod history;
+pub mod recurrence;
+
 pub mod todo {
+    pub use super::recurrence::Recurrence;
+    pub use super::error::{TodoError, TodoResult};
+    pub use super::history::{History, DEFAULT_UNDO_DEPTH};
+    use super::due_date::parse_due_date;
     use chrono::{Duration, NaiveDateTime};
-    #[allow(dead_code)]
     use core::fmt;
     use serde::{Deserialize, Serialize};
-    use std::{error::Error, io::Result as ResultIo};
-    use std::{io::ErrorKind, vec::Vec};
-    #[derive(Debug)]
-    pub enum TodoError {
-        TodoOutOfBoundsError,
-        TodoAddingError,
-    }
+    use std::{cmp::Ordering, str::FromStr, vec::Vec};
+
+    //completed items older than this many days get moved into the archive, archive_days in the config overrides it
+    pub const DEFAULT_ARCHIVE_DAYS: i64 = 30;
+    //archive ages longer than this are most likely a typo
+    pub const MAX_ARCHIVE_DAYS: i64 = 36500;
 
-    impl fmt::Display for TodoError {
-        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-            match self {
-                TodoError::TodoOutOfBoundsError => write!(f, "Input out of bounds"),
-                TodoError::TodoAddingError => write!(f, "Adding failed"),
-            }
-        }
-    }
-    impl Error for TodoError {}
 
-    #[derive(Serialize, Deserialize)]
+    #[derive(Serialize, Deserialize, Clone, PartialEq)]
     pub struct TodoList {
+        //id handed to the next item that gets added, never reused
+        #[serde(default)]
+        pub next_id: usize,
         pub todo_items: Vec<TodoItem>,
         pub completed_items: Vec<TodoItem>,
     }
     impl TodoList {
         pub const fn new() -> TodoList {
             TodoList {
+                next_id: 1,
                 todo_items: Vec::new(),
                 completed_items: Vec::new(),
             }
         }
-        pub fn add_item(&mut self, item_title: &str) -> ResultIo<usize> {
+        pub fn add_item(&mut self, item_title: &str) -> TodoResult<usize> {
+            let id = self.generate_id();
             self.todo_items
-                .push(TodoItem::new(item_title.to_string(), None));
-            return Ok(self.todo_items.len() - 1);
+                .push(TodoItem::new(id, item_title.to_string(), None));
+            return Ok(id);
+        }
+        ///The date can be anything parse_due_date understands, ex: "tomorrow 5pm"
+        pub fn add_item_with_date(&mut self, item_title: &str, date: &str) -> TodoResult<usize> {
+            let date = parse_future_date(date)?;
+            let id = self.generate_id();
+            self.todo_items
+                .push(TodoItem::new(id, item_title.to_string(), Some(date)));
+            return Ok(id);
+        }
+
+        ///Adds an item as the last child of an existing item, at any depth
+        pub fn add_subtask(&mut self, parent_id: usize, item_title: &str) -> TodoResult<usize> {
+            let id = self.generate_id();
+            let parent = match self.find_item_mut(parent_id) {
+                Some(r) => r,
+                None => return Err(TodoError::UnknownId(parent_id)),
+            };
+            parent.children.push(TodoItem::new(id, item_title.to_string(), None));
+            parent.collapsed = false;
+            return Ok(id);
         }
-        pub fn add_item_with_date(&mut self, item_title: &str, date: &str) -> ResultIo<usize> {
-            let date = match NaiveDateTime::parse_from_str(date, "%Y %b %d %H:%M:%S") {
-                Ok(r) => r,
-                Err(_) => return Err(std::io::ErrorKind::Unsupported.into()),
+
+        ///Top level items move over to the completed items, subtasks are completed in place.
+        ///with_subtasks also completes every item below the completed one.
+        ///Recurring items get their next occurrence added right next to them
+        pub fn complete_item(&mut self, item_id: usize, with_subtasks: bool) -> TodoResult<()> {
+            let item = match self.find_item_mut(item_id) {
+                Some(r) if !r.completed => r,
+                Some(_) => return Err(TodoError::invalid_input(&item_id.to_string(), "Item is already completed")),
+                None => return Err(TodoError::UnknownId(item_id)),
             };
 
-            let time_now = chrono::offset::Local::now();
-            if date.signed_duration_since(time_now.naive_local()) < Duration::zero() {
-                return Err(std::io::ErrorKind::Unsupported.into());
+            let now = chrono::offset::Local::now().naive_local();
+            let next_occurrence = item
+                .recurrence
+                .as_ref()
+                .map(|rule| (rule.next_due(item.due_date, now), item.clone()));
+            item.set_completed(true, with_subtasks);
+
+            if let Some((due_date, mut next)) = next_occurrence {
+                self.renew(&mut next);
+                next.due_date = Some(due_date);
+                self.containing_list_mut(item_id).unwrap().push(next);
             }
-            self.todo_items
-                .push(TodoItem::new(item_title.to_string(), Some(date)));
-            return Ok(self.todo_items.len() - 1);
+            self.settle_top_level();
+            return Ok(());
+        }
+        pub fn uncomplete_item(&mut self, item_id: usize) -> TodoResult<()> {
+            let item = match self.find_item_mut(item_id) {
+                Some(r) if r.completed => r,
+                Some(_) => return Err(TodoError::invalid_input(&item_id.to_string(), "Item is not completed")),
+                None => return Err(TodoError::UnknownId(item_id)),
+            };
+
+            item.set_completed(false, false);
+            self.settle_top_level();
+            return Ok(());
+        }
+
+        pub fn set_priority(&mut self, item_id: usize, priority: Priority) -> TodoResult<()> {
+            let item = match self.find_item_mut(item_id) {
+                Some(r) => r,
+                None => return Err(TodoError::UnknownId(item_id)),
+            };
+            item.priority = priority;
+            return Ok(());
         }
 
-        pub fn complete_item(&mut self, item_id: usize) -> ResultIo<()> {
-            if self.todo_items.len() < 1 {
-                return Err(ErrorKind::InvalidInput.into());
+        ///Makes the item a subtask of the sibling shown directly above it
+        pub fn indent_item(&mut self, item_id: usize) -> TodoResult<()> {
+            let siblings = match self.containing_list_mut(item_id) {
+                Some(r) => r,
+                None => return Err(TodoError::UnknownId(item_id)),
+            };
+
+            let mut display_order: Vec<usize> = (0..siblings.len()).collect();
+            display_order.sort_by(|a, b| compare_items(&siblings[*a], &siblings[*b]));
+            let shown_at = display_order
+                .iter()
+                .position(|index| siblings[*index].id == item_id)
+                .unwrap();
+            if shown_at == 0 {
+                return Err(TodoError::invalid_input(&item_id.to_string(), "There is no item above to indent under"));
             }
+            let new_parent_id = siblings[display_order[shown_at - 1]].id;
 
-            if item_id > self.todo_items.len() - 1 {
-                return Err(ErrorKind::InvalidInput.into());
+            let item = siblings.remove(display_order[shown_at]);
+            let new_parent = siblings.iter_mut().find(|item| item.id == new_parent_id).unwrap();
+            new_parent.children.push(item);
+            new_parent.collapsed = false;
+            self.settle_top_level();
+            return Ok(());
+        }
+        ///Moves a subtask up one level, placing it right after its current parent
+        pub fn outdent_item(&mut self, item_id: usize) -> TodoResult<()> {
+            if self.find_parent_mut(item_id).is_none() {
+                return match self.find_item_mut(item_id) {
+                    Some(_) => Err(TodoError::invalid_input(&item_id.to_string(), "Item is not a subtask")),
+                    None => Err(TodoError::UnknownId(item_id)),
+                };
             }
+            let (siblings, parent_index) = match Self::parent_of(&mut self.todo_items, item_id) {
+                Some(r) => r,
+                None => Self::parent_of(&mut self.completed_items, item_id).unwrap(),
+            };
 
-            self.todo_items[item_id].completed = true;
-            self.completed_items.push(self.todo_items.remove(item_id));
+            let children = &mut siblings[parent_index].children;
+            let item = children.remove(Self::position_of(children, item_id).unwrap());
+            siblings.insert(parent_index + 1, item);
+            self.settle_top_level();
             return Ok(());
         }
-        pub fn uncomplete_item(&mut self, item_id: usize) -> ResultIo<()> {
-            if self.completed_items.len() < 1 {
-                return Err(ErrorKind::InvalidInput.into());
+        ///"+project" and "@tag" words in the new title are taken out like when adding an item,
+        ///they are added on top of the item's current project and tags
+        pub fn set_title(&mut self, item_id: usize, item_title: &str) -> TodoResult<()> {
+            let item = match self.find_item_mut(item_id) {
+                Some(r) => r,
+                None => return Err(TodoError::UnknownId(item_id)),
+            };
+            let (title, project, tags) = parse_title(item_title);
+            if title.is_empty() {
+                return Err(TodoError::invalid_input(item_title, "Titles can't be empty"));
+            }
+            item.title = title;
+            if project.is_some() {
+                item.project = project;
             }
+            for tag in tags {
+                if !item.tags.contains(&tag) {
+                    item.tags.push(tag);
+                }
+            }
+            return Ok(());
+        }
+        ///None removes the due date
+        pub fn set_due_date(&mut self, item_id: usize, date: Option<NaiveDateTime>) -> TodoResult<()> {
+            let item = match self.find_item_mut(item_id) {
+                Some(r) => r,
+                None => return Err(TodoError::UnknownId(item_id)),
+            };
+            item.due_date = date;
+            return Ok(());
+        }
+        pub fn set_project(&mut self, item_id: usize, project: Option<String>) -> TodoResult<()> {
+            let item = match self.find_item_mut(item_id) {
+                Some(r) => r,
+                None => return Err(TodoError::UnknownId(item_id)),
+            };
+            item.project = project;
+            return Ok(());
+        }
+        ///Replaces all of the item's tags
+        pub fn set_tags(&mut self, item_id: usize, tags: Vec<String>) -> TodoResult<()> {
+            let item = match self.find_item_mut(item_id) {
+                Some(r) => r,
+                None => return Err(TodoError::UnknownId(item_id)),
+            };
+            item.tags = tags;
+            return Ok(());
+        }
+
+        ///Takes the item, together with its subtasks, out of the list
+        pub fn remove_item(&mut self, item_id: usize) -> TodoResult<TodoItem> {
+            let items = match self.containing_list_mut(item_id) {
+                Some(r) => r,
+                None => return Err(TodoError::UnknownId(item_id)),
+            };
+            let position = Self::position_of(items, item_id).unwrap();
+            return Ok(items.remove(position));
+        }
+
+        ///None stops the item from repeating
+        pub fn set_recurrence(&mut self, item_id: usize, recurrence: Option<Recurrence>) -> TodoResult<()> {
+            let item = match self.find_item_mut(item_id) {
+                Some(r) => r,
+                None => return Err(TodoError::UnknownId(item_id)),
+            };
+            item.recurrence = recurrence;
+            return Ok(());
+        }
+
+        ///Hides or shows the subtasks of an item
+        pub fn toggle_collapsed(&mut self, item_id: usize) -> TodoResult<()> {
+            let item = match self.find_item_mut(item_id) {
+                Some(r) if !r.children.is_empty() => r,
+                Some(_) => return Err(TodoError::invalid_input(&item_id.to_string(), "Item has no subtasks")),
+                None => return Err(TodoError::UnknownId(item_id)),
+            };
+            item.collapsed = !item.collapsed;
+            return Ok(());
+        }
+
+        pub fn find_item_mut(&mut self, item_id: usize) -> Option<&mut TodoItem> {
+            return find_in(&mut self.todo_items, item_id)
+                .or_else(|| find_in(&mut self.completed_items, item_id));
+        }
+
+        ///Moves every item completed more than max_age_days ago into the archive,
+        ///returns how many items were moved
+        pub fn archive_completed(&mut self, max_age_days: i64, archive: &mut Archive) -> usize {
+            let now = chrono::offset::Local::now().naive_local();
+            //nothing was completed before the earliest date there is
+            let cutoff = match Duration::try_days(max_age_days).and_then(|age| now.checked_sub_signed(age)) {
+                Some(r) => r,
+                None => return 0,
+            };
+            let (old, recent): (Vec<TodoItem>, Vec<TodoItem>) = std::mem::take(&mut self.completed_items)
+                .into_iter()
+                .partition(|item| matches!(item.completed_at, Some(done) if done < cutoff));
+
+            self.completed_items = recent;
+            let moved = old.len();
+            archive.items.extend(old);
+            return moved;
+        }
+        ///Takes an item out of the archive and puts it back with the completed items
+        pub fn restore_archived(&mut self, item_id: usize, archive: &mut Archive) -> TodoResult<()> {
+            let index = match Self::position_of(&archive.items, item_id) {
+                Some(r) => r,
+                None => return Err(TodoError::UnknownId(item_id)),
+            };
+
+            self.completed_items.push(archive.items.remove(index));
+            return Ok(());
+        }
+        ///Moves the item, together with its subtasks, into the trash
+        pub fn delete_item(&mut self, item_id: usize, archive: &mut Archive) -> TodoResult<()> {
+            let item = self.remove_item(item_id)?;
+            archive.trash.push(item);
+            return Ok(());
+        }
+        ///Ids that are already gone, ex: subtasks of an item deleted earlier in the list, are skipped.
+        ///Returns how many of the ids were deleted
+        pub fn delete_items(&mut self, item_ids: &[usize], archive: &mut Archive) -> usize {
+            return item_ids
+                .iter()
+                .filter(|item_id| self.delete_item(**item_id, archive).is_ok())
+                .count();
+        }
+        ///Deleted subtasks come back as top level items
+        pub fn restore_deleted(&mut self, item_id: usize, archive: &mut Archive) -> TodoResult<()> {
+            let index = match Self::position_of(&archive.trash, item_id) {
+                Some(r) => r,
+                None => return Err(TodoError::UnknownId(item_id)),
+            };
 
-            if item_id > self.completed_items.len() - 1 {
-                return Err(ErrorKind::InvalidInput.into());
+            let item = archive.trash.remove(index);
+            match item.completed {
+                true => self.completed_items.push(item),
+                false => self.todo_items.push(item),
             }
-            self.completed_items[item_id].completed = false;
-            self.todo_items.push(self.completed_items.remove(item_id));
             return Ok(());
         }
-        pub fn print_list(&self) {
+        ///Gives every item loaded from an older save file (which has no ids yet) a fresh id
+        ///and makes sure next_id is past every id already in use
+        pub fn assign_missing_ids(&mut self) {
+            let highest_id = flatten(&self.todo_items)
+                .into_iter()
+                .chain(flatten(&self.completed_items))
+                .map(|item| item.id)
+                .max()
+                .unwrap_or(0);
+            self.next_id = self.next_id.max(highest_id + 1);
+
+            let mut next_id = self.next_id;
             self.todo_items
+                .iter_mut()
+                .chain(self.completed_items.iter_mut())
+                .filter(|item| item.id == 0)
+                .for_each(|item| {
+                    item.id = next_id;
+                    next_id += 1;
+                });
+            self.next_id = next_id;
+        }
+
+        ///Makes sure ids stored outside of the list (ex: in the archive) are never handed out again
+        pub fn reserve_ids(&mut self, highest_id: usize) {
+            self.next_id = self.next_id.max(highest_id + 1);
+        }
+
+        ///Turns a copy of an item into a fresh uncompleted one, subtasks included
+        fn renew(&mut self, item: &mut TodoItem) {
+            item.id = self.generate_id();
+            item.completed = false;
+            item.completed_at = None;
+            item.created_at = Some(chrono::offset::Local::now().naive_local());
+            item.children.iter_mut().for_each(|child| self.renew(child));
+        }
+
+        ///Adds items read from another format with fresh ids, subtasks included.
+        ///Returns how many items were added
+        pub fn add_imported(&mut self, items: Vec<TodoItem>) -> usize {
+            let mut added = 0;
+            for mut item in items {
+                added += self.give_ids(&mut item);
+                match item.completed {
+                    true => self.completed_items.push(item),
+                    false => self.todo_items.push(item),
+                }
+            }
+            return added;
+        }
+
+        fn give_ids(&mut self, item: &mut TodoItem) -> usize {
+            item.id = self.generate_id();
+            return 1 + item.children.iter_mut().map(|child| self.give_ids(child)).sum::<usize>();
+        }
+
+        fn generate_id(&mut self) -> usize {
+            if self.next_id == 0 {
+                self.next_id = 1;
+            }
+            let id = self.next_id;
+            self.next_id += 1;
+            return id;
+        }
+
+        fn position_of(items: &[TodoItem], item_id: usize) -> Option<usize> {
+            return items.iter().position(|item| item.id == item_id);
+        }
+
+        ///Keeps top level items in the list matching their completed flag
+        fn settle_top_level(&mut self) {
+            let (completed, todo): (Vec<TodoItem>, Vec<TodoItem>) = std::mem::take(&mut self.todo_items)
+                .into_iter()
+                .partition(|item| item.completed);
+            let (uncompleted, still_completed): (Vec<TodoItem>, Vec<TodoItem>) = std::mem::take(&mut self.completed_items)
+                .into_iter()
+                .partition(|item| !item.completed);
+
+            self.todo_items = todo;
+            self.todo_items.extend(uncompleted);
+            self.completed_items = still_completed;
+            self.completed_items.extend(completed);
+        }
+
+        ///The list (top level or some item's children) the item is stored in
+        fn containing_list_mut(&mut self, item_id: usize) -> Option<&mut Vec<TodoItem>> {
+            if Self::position_of(&self.todo_items, item_id).is_some() {
+                return Some(&mut self.todo_items);
+            }
+            if Self::position_of(&self.completed_items, item_id).is_some() {
+                return Some(&mut self.completed_items);
+            }
+            return self
+                .find_parent_mut(item_id)
+                .map(|parent| &mut parent.children);
+        }
+        fn find_parent_mut(&mut self, item_id: usize) -> Option<&mut TodoItem> {
+            return match Self::parent_of(&mut self.todo_items, item_id) {
+                Some((siblings, index)) => Some(&mut siblings[index]),
+                None => Self::parent_of(&mut self.completed_items, item_id)
+                    .map(|(siblings, index)| &mut siblings[index]),
+            };
+        }
+        ///Finds the list holding the parent of the item and the parent's index in it
+        fn parent_of(items: &mut Vec<TodoItem>, item_id: usize) -> Option<(&mut Vec<TodoItem>, usize)> {
+            if let Some(index) = items
                 .iter()
-                .enumerate()
-                .for_each(|(index, item)| {
-                    print!("{index}. ");
-                    item.print();
-                })
-        }
-        pub fn find_item_id(&self, item_title: &str) -> Option<usize> {
-            match self
-                .todo_items
-                .iter()
-                .enumerate()
-                .find(|(_index, item)| item.title == item_title)
+                .position(|item| item.children.iter().any(|child| child.id == item_id))
             {
-                Some((id, _)) => Some(id),
-                None => None,
+                return Some((items, index));
+            }
+            for item in items.iter_mut() {
+                if let Some(found) = Self::parent_of(&mut item.children, item_id) {
+                    return Some(found);
+                }
+            }
+            return None;
+        }
+    }
+
+    ///Parses a due date and makes sure it has not already passed
+    pub fn parse_future_date(date: &str) -> TodoResult<NaiveDateTime> {
+        let time_now = chrono::offset::Local::now().naive_local();
+        let date = parse_due_date(date, time_now)?;
+
+        if date.signed_duration_since(time_now) < Duration::zero() {
+            return Err(TodoError::PastDueDate(date));
+        }
+        return Ok(date);
+    }
+
+    ///Order items are shown in: highest priority first, then by the earliest due date
+    pub fn compare_items(a: &TodoItem, b: &TodoItem) -> Ordering {
+        return b.priority
+            .cmp(&a.priority)
+            .then_with(|| match (a.due_date, b.due_date) {
+                (Some(a_due), Some(b_due)) => a_due.cmp(&b_due),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            });
+    }
+
+    fn find_in(items: &mut [TodoItem], item_id: usize) -> Option<&mut TodoItem> {
+        for item in items.iter_mut() {
+            if item.id == item_id {
+                return Some(item);
+            }
+            if let Some(found) = find_in(&mut item.children, item_id) {
+                return Some(found);
+            }
+        }
+        return None;
+    }
+
+    ///Every item in the slice plus all of their subtasks
+    pub fn flatten(items: &[TodoItem]) -> Vec<&TodoItem> {
+        let mut flattened = Vec::new();
+        items.iter().for_each(|item| {
+            flattened.push(item);
+            flattened.extend(flatten(&item.children));
+        });
+        return flattened;
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Default)]
+    pub enum Priority {
+        #[default]
+        None,
+        Low,
+        Medium,
+        High,
+        Urgent,
+    }
+    impl Priority {
+        ///Single letter shown in front of the title, None has no marker
+        pub fn marker(&self) -> Option<char> {
+            match self {
+                Priority::None => None,
+                Priority::Low => Some('L'),
+                Priority::Medium => Some('M'),
+                Priority::High => Some('H'),
+                Priority::Urgent => Some('U'),
+            }
+        }
+    }
+    impl FromStr for Priority {
+        type Err = TodoError;
+
+        ///Accepts the full name, its first letter or a number from 0 (none) to 4 (urgent)
+        fn from_str(s: &str) -> TodoResult<Priority> {
+            match &*s.to_lowercase() {
+                "none" | "n" | "0" => Ok(Priority::None),
+                "low" | "l" | "1" => Ok(Priority::Low),
+                "medium" | "m" | "2" => Ok(Priority::Medium),
+                "high" | "h" | "3" => Ok(Priority::High),
+                "urgent" | "u" | "4" => Ok(Priority::Urgent),
+                _ => Err(TodoError::invalid_input(s, "Expected none, low, medium, high or urgent")),
             }
         }
-        pub fn todo_len(&self) -> usize {
-            return self.todo_items.len();
+    }
+
+    impl fmt::Display for Priority {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let name = match self {
+                Priority::None => "none",
+                Priority::Low => "low",
+                Priority::Medium => "medium",
+                Priority::High => "high",
+                Priority::Urgent => "urgent",
+            };
+            write!(f, "{name}")
         }
-        pub fn completed_len(&self) -> usize {
-            return self.completed_items.len();
+    }
+
+    ///Restricts the shown items to a project and/or a set of tags, an empty filter shows everything
+    #[derive(Default, Clone)]
+    pub struct Filter {
+        pub project: Option<String>,
+        pub tags: Vec<String>,
+    }
+    impl Filter {
+        pub fn matches(&self, item: &TodoItem) -> bool {
+            if self.project.is_some() && self.project != item.project {
+                return false;
+            }
+            return self.tags.iter().all(|tag| item.tags.contains(tag));
+        }
+        pub fn is_empty(&self) -> bool {
+            return self.project.is_none() && self.tags.is_empty();
         }
     }
+    impl FromStr for Filter {
+        type Err = TodoError;
 
-    #[derive(Serialize, Deserialize)]
+        ///Same syntax as in titles, ex: "+backend @review"
+        fn from_str(s: &str) -> TodoResult<Filter> {
+            let (rest, project, tags) = parse_title(s);
+            if !rest.is_empty() {
+                return Err(TodoError::invalid_input(&rest, "Filters can only contain +project and @tag words"));
+            }
+            return Ok(Filter { project, tags });
+        }
+    }
+    impl fmt::Display for Filter {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let mut parts: Vec<String> = self.project.iter().map(|project| format!("+{project}")).collect();
+            parts.extend(self.tags.iter().map(|tag| format!("@{tag}")));
+            write!(f, "{}", parts.join(" "))
+        }
+    }
+
+    ///Splits "+project" and "@tag" words out of a title,
+    ///returns the remaining title, the project (the first one wins) and the tags
+    pub fn parse_title(input: &str) -> (String, Option<String>, Vec<String>) {
+        let mut title_words = Vec::new();
+        let mut project = None;
+        let mut tags: Vec<String> = Vec::new();
+
+        for word in input.split_whitespace() {
+            match (word.strip_prefix('+'), word.strip_prefix('@')) {
+                (Some(name), _) if !name.is_empty() => {
+                    project.get_or_insert_with(|| name.to_string());
+                }
+                (_, Some(name)) if !name.is_empty() => {
+                    if !tags.iter().any(|tag| tag == name) {
+                        tags.push(name.to_string());
+                    }
+                }
+                _ => title_words.push(word),
+            }
+        }
+        return (title_words.join(" "), project, tags);
+    }
+
+    ///Where items that left the live list are kept
+    #[derive(Copy, Clone, PartialEq)]
+    pub enum Shelf {
+        Archive,
+        Trash,
+    }
+
+    impl fmt::Display for Shelf {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                Shelf::Archive => write!(f, "Archive"),
+                Shelf::Trash => write!(f, "Trash"),
+            }
+        }
+    }
+
+    ///Items that have been moved out of the live list, stored in their own file.
+    ///Old completed items go into items, deleted ones into the trash
+    #[derive(Serialize, Deserialize, Default, Clone, PartialEq)]
+    pub struct Archive {
+        pub items: Vec<TodoItem>,
+        #[serde(default)]
+        pub trash: Vec<TodoItem>,
+    }
+    impl Archive {
+        pub fn shelf(&self, shelf: Shelf) -> &Vec<TodoItem> {
+            return match shelf {
+                Shelf::Archive => &self.items,
+                Shelf::Trash => &self.trash,
+            };
+        }
+        ///Case insensitive search through the titles of the items on a shelf
+        pub fn search<'a>(&'a self, shelf: Shelf, query: &str) -> impl Iterator<Item = &'a TodoItem> + 'a {
+            let query = query.to_lowercase();
+            return self
+                .shelf(shelf)
+                .iter()
+                .filter(move |item| item.title.to_lowercase().contains(&query));
+        }
+        pub fn highest_id(&self) -> usize {
+            return flatten(&self.items)
+                .into_iter()
+                .chain(flatten(&self.trash))
+                .map(|item| item.id)
+                .max()
+                .unwrap_or(0);
+        }
+        pub fn empty_trash(&mut self) -> usize {
+            return std::mem::take(&mut self.trash).len();
+        }
+    }
+
+    #[derive(Serialize, Deserialize, Clone, PartialEq)]
     pub struct TodoItem {
+        //0 is never handed out, it marks items from save files that predate ids
+        #[serde(default)]
+        pub id: usize,
         pub title: String,
         pub completed: bool,
         pub due_date: Option<NaiveDateTime>,
+        #[serde(default)]
+        pub completed_at: Option<NaiveDateTime>,
+        //None for items from before creation dates were kept
+        #[serde(default)]
+        pub created_at: Option<NaiveDateTime>,
+        #[serde(default)]
+        pub priority: Priority,
+        #[serde(default)]
+        pub project: Option<String>,
+        #[serde(default)]
+        pub tags: Vec<String>,
+        #[serde(default)]
+        pub children: Vec<TodoItem>,
+        //hides the children in the TUI
+        #[serde(default)]
+        pub collapsed: bool,
+        #[serde(default)]
+        pub recurrence: Option<Recurrence>,
     }
     impl TodoItem {
-        fn new(item_title: String, due_date: Option<NaiveDateTime>) -> TodoItem {
+        ///Any "+project" or "@tag" words in the title are taken out and stored separately
+        pub fn new(id: usize, item_title: String, due_date: Option<NaiveDateTime>) -> TodoItem {
+            let (title, project, tags) = parse_title(&item_title);
             TodoItem {
-                title: item_title,
+                id,
+                title,
                 completed: false,
                 due_date,
+                completed_at: None,
+                created_at: Some(chrono::offset::Local::now().naive_local()),
+                priority: Priority::None,
+                project,
+                tags,
+                children: Vec::new(),
+                collapsed: false,
+                recurrence: None,
             }
         }
-        pub fn print(&self) {
-            print!("{item}: ", item = self.title);
-            match self.completed {
-                false => println!("[]"),
-                true => println!("[X]"),
+        fn set_completed(&mut self, completed: bool, with_subtasks: bool) {
+            self.completed = completed;
+            self.completed_at = match completed {
+                true => Some(chrono::offset::Local::now().naive_local()),
+                false => None,
+            };
+            if with_subtasks {
+                self.children
+                    .iter_mut()
+                    .filter(|child| child.completed != completed)
+                    .for_each(|child| child.set_completed(completed, true));
             }
         }
+        ///Completed and total amount of subtasks at every depth, None without subtasks
+        pub fn progress(&self) -> Option<(usize, usize)> {
+            if self.children.is_empty() {
+                return None;
+            }
+            let subtasks = flatten(&self.children);
+            let completed = subtasks.iter().filter(|item| item.completed).count();
+            return Some((completed, subtasks.len()));
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::{Priority, TodoError, TodoItem, TodoList};
+
+        fn titles(items: &[TodoItem]) -> Vec<&str> {
+            return items.iter().map(|item| item.title.as_str()).collect();
+        }
+
+        #[test]
+        fn indenting_moves_under_the_item_shown_above() {
+            let mut todo = TodoList::new();
+            let a = todo.add_item("a").unwrap();
+            let b = todo.add_item("b").unwrap();
+            let c = todo.add_item("c").unwrap();
+            //c is shown first, above a
+            todo.set_priority(c, Priority::High).unwrap();
+            todo.indent_item(a).unwrap();
+            assert_eq!(titles(&todo.todo_items), ["b", "c"]);
+            assert_eq!(titles(&todo.todo_items[1].children), ["a"]);
+
+            todo.indent_item(b).unwrap();
+            assert_eq!(titles(&todo.todo_items), ["c"]);
+            assert_eq!(titles(&todo.todo_items[0].children), ["a", "b"]);
+            assert!(matches!(todo.indent_item(c), Err(TodoError::InvalidInput { .. })));
+            assert!(matches!(todo.indent_item(9), Err(TodoError::UnknownId(9))));
+        }
+
+        #[test]
+        fn outdenting_places_the_item_after_its_parent() {
+            let mut todo = TodoList::new();
+            let a = todo.add_item("a").unwrap();
+            todo.add_item("b").unwrap();
+            let a1 = todo.add_subtask(a, "a1").unwrap();
+            let a11 = todo.add_subtask(a1, "a11").unwrap();
+            todo.add_subtask(a, "a2").unwrap();
+            todo.outdent_item(a1).unwrap();
+            assert_eq!(titles(&todo.todo_items), ["a", "a1", "b"]);
+            assert_eq!(titles(&todo.todo_items[0].children), ["a2"]);
+
+            todo.outdent_item(a11).unwrap();
+            assert_eq!(titles(&todo.todo_items), ["a", "a1", "a11", "b"]);
+            assert!(matches!(todo.outdent_item(a), Err(TodoError::InvalidInput { .. })));
+            assert!(matches!(todo.outdent_item(9), Err(TodoError::UnknownId(9))));
+        }
+
+        #[test]
+        fn top_level_items_settle_where_they_belong() {
+            let mut todo = TodoList::new();
+            let a = todo.add_item("a").unwrap();
+            let a1 = todo.add_subtask(a, "a1").unwrap();
+            todo.add_item("b").unwrap();
+            //a completed subtask moved to the top goes to the completed items
+            todo.complete_item(a1, false).unwrap();
+            todo.outdent_item(a1).unwrap();
+            assert_eq!(titles(&todo.todo_items), ["a", "b"]);
+            assert_eq!(titles(&todo.completed_items), ["a1"]);
+
+            //items whose flag changed in place switch lists
+            todo.complete_item(a, false).unwrap();
+            assert_eq!(titles(&todo.completed_items), ["a1", "a"]);
+            todo.todo_items[0].completed = true;
+            todo.completed_items[0].completed = false;
+            todo.settle_top_level();
+            assert_eq!(titles(&todo.todo_items), ["a1"]);
+            assert_eq!(titles(&todo.completed_items), ["a", "b"]);
+        }
     }
 }
@@ -0,0 +1,336 @@
+use chrono::{Datelike, Duration, Local, Month, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+use super::error::{TodoError, TodoResult};
+
+//time used when only a date is given
+pub const DEFAULT_DUE_TIME: (u32, u32) = (23, 59);
+
+//formats tried on the whole input before it gets split into words
+const FULL_FORMATS: [&str; 5] = [
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%d %H:%M",
+    "%Y %b %d %H:%M:%S",
+];
+
+///Parses a due date relative to now. Understands ISO 8601 ("2023-05-12T17:00"),
+///relative dates ("in 3 days", "2w", "tomorrow 5pm", "next fri", "eod"),
+///month names ("may 12 2023 12:00") and date only input, which defaults to DEFAULT_DUE_TIME.
+///The error names the part of the input that was not understood
+pub fn parse_due_date(input: &str, now: NaiveDateTime) -> TodoResult<NaiveDateTime> {
+    let input = input.trim();
+    return parse_words(input, now).map_err(|reason| TodoError::invalid_date(input, reason));
+}
+
+//errors are the reason the date could not be read
+type DateResult<T> = Result<T, String>;
+
+fn parse_words(input: &str, now: NaiveDateTime) -> DateResult<NaiveDateTime> {
+    if let Ok(date) = chrono::DateTime::parse_from_rfc3339(input) {
+        return Ok(date.with_timezone(&Local).naive_local());
+    }
+    if let Some(date) = FULL_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(input, format).ok())
+    {
+        return Ok(date);
+    }
+
+    let lowercase = input.to_lowercase();
+    let words: Vec<&str> = lowercase
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|word| !word.is_empty())
+        .collect();
+    if words.is_empty() {
+        return Err("No date was given".to_owned());
+    }
+
+    let mut parts = DateParts::default();
+    let mut index = 0;
+    while index < words.len() {
+        index += parts.read_word(&words, index, now.date())?;
+    }
+    return parts.resolve(now);
+}
+
+#[derive(Default)]
+struct DateParts {
+    date: Option<NaiveDate>,
+    time: Option<NaiveTime>,
+    offset: Option<Duration>,
+    year: Option<i32>,
+    month: Option<u32>,
+    day: Option<u32>,
+}
+
+impl DateParts {
+    ///Reads the word at index (and any words belonging to it), returns how many words were used
+    fn read_word(&mut self, words: &[&str], index: usize, today: NaiveDate) -> DateResult<usize> {
+        let word = words[index];
+        match word {
+            "at" | "on" | "by" => return Ok(1),
+            "today" => self.set_date(today)?,
+            "tomorrow" | "tmrw" => self.set_date(today + Duration::days(1))?,
+            "eod" => {
+                self.set_date(today)?;
+                self.time.get_or_insert_with(default_time);
+            }
+            "noon" => self.set_time(NaiveTime::from_hms_opt(12, 0, 0).unwrap())?,
+            "midnight" => self.set_time(NaiveTime::from_hms_opt(0, 0, 0).unwrap())?,
+            "in" => {
+                let amount = words.get(index + 1).map(|amount| amount.parse::<i64>());
+                let unit = words.get(index + 2).and_then(|unit| parse_unit(unit));
+                return match (amount, unit) {
+                    (Some(Ok(amount)), Some(unit)) => {
+                        self.add_offset(unit(amount).ok_or_else(too_far)?)?;
+                        Ok(3)
+                    }
+                    _ => Err(format!(
+                        "Expected an amount and a unit after 'in', ex: 'in 3 days' (got '{}')",
+                        words[index..].join(" ")
+                    )),
+                };
+            }
+            "next" => {
+                return match words.get(index + 1) {
+                    Some(&"week") => {
+                        self.set_date(today + Duration::weeks(1))?;
+                        Ok(2)
+                    }
+                    Some(next) => match parse_weekday(next) {
+                        Some(weekday) => {
+                            self.set_date(coming_weekday(today, weekday))?;
+                            Ok(2)
+                        }
+                        None => Err(format!("Expected a weekday or 'week' after 'next', got '{next}'")),
+                    },
+                    None => Err("Expected a weekday or 'week' after 'next'".to_owned()),
+                };
+            }
+            _ => self.read_value(word, today)?,
+        }
+        return Ok(1);
+    }
+
+    fn read_value(&mut self, word: &str, today: NaiveDate) -> DateResult<()> {
+        if let Some(weekday) = parse_weekday(word) {
+            return self.set_date(coming_weekday(today, weekday));
+        }
+        if let Ok(date) = NaiveDate::parse_from_str(word, "%Y-%m-%d") {
+            return self.set_date(date);
+        }
+        if let Some(time) = parse_time(word) {
+            return self.set_time(time);
+        }
+        if let Some(offset) = parse_short_offset(word) {
+            return self.add_offset(offset.ok_or_else(too_far)?);
+        }
+        //full names or the three letter ones, so words like "junk" aren't read as june
+        if let Ok(month) = word.parse::<Month>() {
+            self.month = Some(month.number_from_month());
+            return Ok(());
+        }
+
+        let number = word.trim_end_matches(|c: char| c.is_alphabetic());
+        match number.parse::<u32>() {
+            Ok(year) if word.len() == 4 && year >= 1000 => self.year = Some(year as i32),
+            Ok(day) if (1..=31).contains(&day) && self.day.is_none() => self.day = Some(day),
+            _ => return Err(format!("Did not understand '{word}'")),
+        }
+        return Ok(());
+    }
+
+    fn set_date(&mut self, date: NaiveDate) -> DateResult<()> {
+        if self.date.is_some() {
+            return Err("More than one date was given".to_owned());
+        }
+        self.date = Some(date);
+        return Ok(());
+    }
+
+    fn set_time(&mut self, time: NaiveTime) -> DateResult<()> {
+        if self.time.is_some() {
+            return Err("More than one time was given".to_owned());
+        }
+        self.time = Some(time);
+        return Ok(());
+    }
+
+    fn add_offset(&mut self, offset: Duration) -> DateResult<()> {
+        let total = self.offset.unwrap_or_else(Duration::zero).checked_add(&offset);
+        self.offset = Some(total.ok_or_else(too_far)?);
+        return Ok(());
+    }
+
+    fn resolve(self, now: NaiveDateTime) -> DateResult<NaiveDateTime> {
+        let today = now.date();
+        let calendar_date = match (self.month, self.day) {
+            (Some(month), Some(day)) => {
+                let year = self.year.unwrap_or(today.year());
+                let date = match NaiveDate::from_ymd_opt(year, month, day) {
+                    Some(r) => r,
+                    None => return Err(format!("{year}-{month:0>2}-{day:0>2} is not a real date")),
+                };
+                //"may 12" without a year means the next may 12
+                match self.year.is_none() && date < today {
+                    true => NaiveDate::from_ymd_opt(year + 1, month, day),
+                    false => Some(date),
+                }
+            }
+            (None, None) if self.year.is_none() => None,
+            _ => return Err("A date needs both a month and a day".to_owned()),
+        };
+        let date = match (self.date, calendar_date) {
+            (Some(_), Some(_)) => return Err("More than one date was given".to_owned()),
+            (date, calendar_date) => date.or(calendar_date),
+        };
+
+        return match (date, self.time, self.offset) {
+            (Some(date), time, None) => Ok(date.and_time(time.unwrap_or_else(default_time))),
+            (None, None, Some(offset)) => now.checked_add_signed(offset).ok_or_else(too_far),
+            (None, Some(time), offset) => {
+                let offset = offset.unwrap_or_else(Duration::zero);
+                let due = now.checked_add_signed(offset).ok_or_else(too_far)?.date().and_time(time);
+                //a time on its own means the next time the clock shows it
+                match offset.is_zero() && due <= now {
+                    true => Ok(due + Duration::days(1)),
+                    false => Ok(due),
+                }
+            }
+            (Some(_), _, Some(_)) => Err("A date can't be combined with a relative offset".to_owned()),
+            (None, None, None) => Err("No date was given".to_owned()),
+        };
+    }
+}
+
+fn too_far() -> String {
+    return "The date is too far away".to_owned();
+}
+
+fn default_time() -> NaiveTime {
+    return NaiveTime::from_hms_opt(DEFAULT_DUE_TIME.0, DEFAULT_DUE_TIME.1, 0).unwrap();
+}
+
+fn coming_weekday(today: NaiveDate, weekday: Weekday) -> NaiveDate {
+    let mut date = today + Duration::days(1);
+    while date.weekday() != weekday {
+        date += Duration::days(1);
+    }
+    return date;
+}
+
+fn parse_weekday(word: &str) -> Option<Weekday> {
+    if word.len() < 3 || word.chars().any(|c| !c.is_alphabetic()) {
+        return None;
+    }
+    return word.parse::<Weekday>().ok();
+}
+
+///Units can be plural, ex: "days" or "hrs". The unit gives None for amounts too big for a Duration
+fn parse_unit(unit: &str) -> Option<fn(i64) -> Option<Duration>> {
+    return match unit.trim_end_matches('s') {
+        "m" | "min" | "minute" => Some(Duration::try_minutes),
+        "h" | "hr" | "hour" => Some(Duration::try_hours),
+        "d" | "day" => Some(Duration::try_days),
+        "w" | "wk" | "week" => Some(Duration::try_weeks),
+        _ => None,
+    };
+}
+
+///Shorthand offsets like "2w", "3d" or "90m", Some(None) for ones too big for a Duration
+fn parse_short_offset(word: &str) -> Option<Option<Duration>> {
+    let split = word.find(|c: char| !c.is_ascii_digit())?;
+    let (amount, unit) = word.split_at(split);
+    let amount = amount.parse::<i64>().ok()?;
+    return parse_unit(unit).map(|unit| unit(amount));
+}
+
+///"5pm", "5:30pm", "17:00" and "17:00:00"
+fn parse_time(word: &str) -> Option<NaiveTime> {
+    let (clock, pm) = match (word.strip_suffix("am"), word.strip_suffix("pm")) {
+        (Some(clock), _) => (clock, Some(false)),
+        (_, Some(clock)) => (clock, Some(true)),
+        _ => (word, None),
+    };
+    if pm.is_none() && !clock.contains(':') {
+        return None;
+    }
+
+    let mut numbers = clock.split(':').map(|number| number.parse::<u32>());
+    let mut hour = numbers.next()?.ok()?;
+    let minute = numbers.next().unwrap_or(Ok(0)).ok()?;
+    let second = numbers.next().unwrap_or(Ok(0)).ok()?;
+    if numbers.next().is_some() {
+        return None;
+    }
+    if let Some(pm) = pm {
+        if !(1..=12).contains(&hour) {
+            return None;
+        }
+        hour = match (hour, pm) {
+            (12, false) => 0,
+            (12, true) => 12,
+            (hour, true) => hour + 12,
+            (hour, false) => hour,
+        };
+    }
+    return NaiveTime::from_hms_opt(hour, minute, second);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_due_date;
+    use crate::todo_backend::todo::TodoError;
+    use chrono::{NaiveDate, NaiveDateTime};
+
+    //a friday
+    fn now() -> NaiveDateTime {
+        return at(2023, 5, 12, 17, 0);
+    }
+
+    fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        return NaiveDate::from_ymd_opt(year, month, day).unwrap().and_hms_opt(hour, minute, 0).unwrap();
+    }
+
+    #[test]
+    fn reads_iso_dates() {
+        assert_eq!(parse_due_date("2023-05-20T09:30", now()).unwrap(), at(2023, 5, 20, 9, 30));
+        assert_eq!(parse_due_date("2023-05-20 09:30", now()).unwrap(), at(2023, 5, 20, 9, 30));
+        assert_eq!(parse_due_date(" 2023-05-20 ", now()).unwrap(), at(2023, 5, 20, 23, 59));
+    }
+
+    #[test]
+    fn reads_relative_dates() {
+        assert_eq!(parse_due_date("tomorrow 5pm", now()).unwrap(), at(2023, 5, 13, 17, 0));
+        assert_eq!(parse_due_date("in 3 days", now()).unwrap(), at(2023, 5, 15, 17, 0));
+        assert_eq!(parse_due_date("2w", now()).unwrap(), at(2023, 5, 26, 17, 0));
+        assert_eq!(parse_due_date("eod", now()).unwrap(), at(2023, 5, 12, 23, 59));
+        //the coming one, never today
+        assert_eq!(parse_due_date("next fri", now()).unwrap(), at(2023, 5, 19, 23, 59));
+        assert_eq!(parse_due_date("mon noon", now()).unwrap(), at(2023, 5, 15, 12, 0));
+    }
+
+    #[test]
+    fn times_and_dates_that_passed_move_ahead() {
+        assert_eq!(parse_due_date("3pm", now()).unwrap(), at(2023, 5, 13, 15, 0));
+        assert_eq!(parse_due_date("6pm", now()).unwrap(), at(2023, 5, 12, 18, 0));
+        assert_eq!(parse_due_date("may 1", now()).unwrap(), at(2024, 5, 1, 23, 59));
+        assert_eq!(parse_due_date("may 1 2023 12:00", now()).unwrap(), at(2023, 5, 1, 12, 0));
+        assert_eq!(parse_due_date("June 3", now()).unwrap(), at(2023, 6, 3, 23, 59));
+        assert_eq!(parse_due_date("3 dec", now()).unwrap(), at(2023, 12, 3, 23, 59));
+    }
+
+    #[test]
+    fn refuses_invalid_dates() {
+        for input in ["", "someday", "junk 12", "decent 3", "sept 12", "2023-02-30", "feb 30", "tomorrow 2023-05-20", "2023-05-20 in 3 days", "13pm"] {
+            assert!(parse_due_date(input, now()).is_err(), "{input}");
+        }
+    }
+
+    #[test]
+    fn refuses_offsets_too_big_for_a_date() {
+        for input in ["in 99999999999 days", "99999999999999d", "in 9223372036854775807 weeks", "99999999999w 5pm", "2w 9999999999999w"] {
+            assert!(matches!(parse_due_date(input, now()), Err(TodoError::InvalidDate { .. })), "{input}");
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use chrono::NaiveDateTime;
+use core::fmt;
+use std::{error::Error, io};
+
+pub type TodoResult<T> = Result<T, TodoError>;
+
+///Everything that can go wrong while working with the list,
+///the Display output is what gets shown to the user in the command bar
+#[derive(Debug)]
+pub enum TodoError {
+    //position is the character offset into input where expected was missing
+    Parse {
+        input: String,
+        position: usize,
+        expected: String,
+    },
+    UnknownCommand(String),
+    UnknownId(usize),
+    InvalidDate {
+        input: String,
+        reason: String,
+    },
+    PastDueDate(NaiveDateTime),
+    //input that was understood but can't be applied, ex: indenting the first item
+    InvalidInput {
+        input: String,
+        reason: String,
+    },
+    //config.toml could be read but not understood
+    Config {
+        file: String,
+        reason: String,
+    },
+    //the save file is from a newer build that may have written things this one would drop
+    NewerVersion {
+        file: String,
+        version: u64,
+        supported: u64,
+    },
+    //the save file was changed by another program since it was loaded, holds the path
+    Conflict(String),
+    Io(io::Error),
+    Serialization(serde_json::Error),
+}
+
+impl TodoError {
+    pub fn parse(input: &str, position: usize, expected: &str) -> TodoError {
+        return TodoError::Parse {
+            input: input.to_owned(),
+            position,
+            expected: expected.to_owned(),
+        };
+    }
+    pub fn invalid_date(input: &str, reason: String) -> TodoError {
+        return TodoError::InvalidDate {
+            input: input.to_owned(),
+            reason,
+        };
+    }
+    pub fn invalid_input(input: &str, reason: &str) -> TodoError {
+        return TodoError::InvalidInput {
+            input: input.to_owned(),
+            reason: reason.to_owned(),
+        };
+    }
+    ///Errors the TUI can't recover from, everything else is shown in the command bar
+    pub fn is_fatal(&self) -> bool {
+        return matches!(self, TodoError::Io(_) | TodoError::Serialization(_));
+    }
+}
+
+impl fmt::Display for TodoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TodoError::Parse { input, position, expected } => {
+                write!(f, "Expected {expected} at position {position} in '{input}'")
+            }
+            TodoError::UnknownCommand(command) => write!(f, "Unknown command '{command}'"),
+            TodoError::UnknownId(id) => write!(f, "There is no item with id {id}"),
+            TodoError::InvalidDate { input, reason } => write!(f, "Invalid date '{input}': {reason}"),
+            TodoError::PastDueDate(date) => {
+                write!(f, "The due date {} is in the past", date.format("%b %d %Y %H:%M"))
+            }
+            TodoError::InvalidInput { input, reason } => write!(f, "{reason}: '{input}'"),
+            TodoError::Config { file, reason } => write!(f, "Invalid config file '{file}': {reason}"),
+            TodoError::NewerVersion { file, version, supported } => write!(
+                f,
+                "'{file}' was saved by a newer version of todo (format {version}, this one reads up to {supported}), update todo to open it"
+            ),
+            TodoError::Conflict(file) => {
+                write!(f, "'{file}' was changed by another program since it was loaded")
+            }
+            TodoError::Io(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+                write!(f, "App does not have permission to access save file: {e}")
+            }
+            TodoError::Io(e) => write!(f, "Reading or writing the save file failed: {e}"),
+            TodoError::Serialization(e) => write!(f, "Save file is not valid: {e}"),
+        }
+    }
+}
+
+impl Error for TodoError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TodoError::Io(e) => Some(e),
+            TodoError::Serialization(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for TodoError {
+    fn from(e: io::Error) -> TodoError {
+        return TodoError::Io(e);
+    }
+}
+
+impl From<serde_json::Error> for TodoError {
+    fn from(e: serde_json::Error) -> TodoError {
+        return TodoError::Serialization(e);
+    }
+}
@@ -0,0 +1,293 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use super::error::{TodoError, TodoResult};
+use super::todo::{Archive, TodoItem, TodoList};
+
+//how many changes can be undone unless configured otherwise
+pub const DEFAULT_UNDO_DEPTH: usize = 100;
+
+//the item lists a change can touch
+#[derive(Serialize, Deserialize, Clone, Copy)]
+enum Part {
+    Todo,
+    Completed,
+    Archived,
+    Trash,
+}
+impl Part {
+    const ALL: [Part; 4] = [Part::Todo, Part::Completed, Part::Archived, Part::Trash];
+
+    fn items<'a>(self, todo: &'a TodoList, archive: &'a Archive) -> &'a Vec<TodoItem> {
+        return match self {
+            Part::Todo => &todo.todo_items,
+            Part::Completed => &todo.completed_items,
+            Part::Archived => &archive.items,
+            Part::Trash => &archive.trash,
+        };
+    }
+    fn items_mut<'a>(self, todo: &'a mut TodoList, archive: &'a mut Archive) -> &'a mut Vec<TodoItem> {
+        return match self {
+            Part::Todo => &mut todo.todo_items,
+            Part::Completed => &mut todo.completed_items,
+            Part::Archived => &mut archive.items,
+            Part::Trash => &mut archive.trash,
+        };
+    }
+}
+
+///Replaces count top level items from start with items
+#[derive(Serialize, Deserialize)]
+struct Splice {
+    part: Part,
+    start: usize,
+    count: usize,
+    items: Vec<TodoItem>,
+}
+impl Splice {
+    ///The splice turning after back into before, None if they are the same.
+    ///Only the items between the unchanged ones at both ends are kept
+    fn between(part: Part, before: &[TodoItem], after: &[TodoItem]) -> Option<Splice> {
+        let start = before.iter().zip(after).take_while(|(a, b)| a == b).count();
+        let end = before[start..]
+            .iter()
+            .rev()
+            .zip(after[start..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        if before.len() == after.len() && start == before.len() {
+            return None;
+        }
+        return Some(Splice {
+            part,
+            start,
+            count: after.len() - start - end,
+            items: before[start..before.len() - end].to_vec(),
+        });
+    }
+    ///Applies the splice and returns the one that reverts it
+    fn apply(self, todo: &mut TodoList, archive: &mut Archive) -> Splice {
+        let items = self.part.items_mut(todo, archive);
+        let count = self.items.len();
+        let replaced = items.splice(self.start..self.start + self.count, self.items).collect();
+        return Splice { part: self.part, start: self.start, count, items: replaced };
+    }
+}
+
+///The difference a change made, applying it turns the list and archive back into what they were
+#[derive(Serialize, Deserialize)]
+struct Change {
+    splices: Vec<Splice>,
+    next_id: usize,
+}
+impl Change {
+    fn between((old_todo, old_archive): &(TodoList, Archive), todo: &TodoList, archive: &Archive) -> Option<Change> {
+        let splices: Vec<Splice> = Part::ALL
+            .iter()
+            .filter_map(|part| Splice::between(*part, part.items(old_todo, old_archive), part.items(todo, archive)))
+            .collect();
+        if splices.is_empty() && old_todo.next_id == todo.next_id {
+            return None;
+        }
+        return Some(Change { splices, next_id: old_todo.next_id });
+    }
+    ///Applies the change and returns the one that reverts it
+    fn apply(self, todo: &mut TodoList, archive: &mut Archive) -> Change {
+        let next_id = std::mem::replace(&mut todo.next_id, self.next_id);
+        //every part has one splice at most, so they don't depend on each other
+        let splices = self.splices.into_iter().map(|splice| splice.apply(todo, archive)).collect();
+        return Change { splices, next_id };
+    }
+}
+
+///Undo and redo stacks for every change made to the list, each step only keeps what it changed.
+///A change is recorded by calling begin before it and commit after it
+#[derive(Serialize, Deserialize)]
+pub struct History {
+    #[serde(skip, default = "default_depth")]
+    depth: usize,
+    undo: VecDeque<Change>,
+    redo: Vec<Change>,
+    #[serde(skip)]
+    pending: Option<(TodoList, Archive)>,
+}
+
+fn default_depth() -> usize {
+    return DEFAULT_UNDO_DEPTH;
+}
+
+impl History {
+    pub fn new(depth: usize) -> History {
+        return History {
+            depth,
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            pending: None,
+        };
+    }
+    ///Drops the oldest changes that no longer fit
+    pub fn set_depth(&mut self, depth: usize) {
+        self.depth = depth;
+        while self.undo.len() > depth {
+            self.undo.pop_front();
+        }
+        self.redo.truncate(depth);
+    }
+
+    pub fn begin(&mut self, todo: &TodoList, archive: &Archive) {
+        self.pending = Some((todo.clone(), archive.clone()));
+    }
+    ///Records the change since begin, if anything changed at all
+    pub fn commit(&mut self, todo: &TodoList, archive: &Archive) {
+        let change = match self.pending.take() {
+            Some(before) => Change::between(&before, todo, archive),
+            None => None,
+        };
+        let Some(change) = change else {
+            return;
+        };
+        if self.depth == 0 {
+            return;
+        }
+        if self.undo.len() == self.depth {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(change);
+        self.redo.clear();
+    }
+
+    ///Undoes the last times changes, nothing is undone when there are fewer
+    pub fn undo(&mut self, todo: &mut TodoList, archive: &mut Archive, times: usize) -> TodoResult<()> {
+        //undoing in the middle of a change leaves nothing to commit
+        self.pending = None;
+        check_steps(self.undo.len(), times, "undo", "undone")?;
+        for _ in 0..times {
+            let change = self.undo.pop_back().unwrap();
+            self.redo.push(change.apply(todo, archive));
+        }
+        return Ok(());
+    }
+
+    ///Redoes the last times undone changes, nothing is redone when there are fewer
+    pub fn redo(&mut self, todo: &mut TodoList, archive: &mut Archive, times: usize) -> TodoResult<()> {
+        self.pending = None;
+        check_steps(self.redo.len(), times, "redo", "redone")?;
+        for _ in 0..times {
+            let change = self.redo.pop().unwrap();
+            self.undo.push_back(change.apply(todo, archive));
+        }
+        return Ok(());
+    }
+}
+
+fn check_steps(available: usize, times: usize, action: &str, done: &str) -> TodoResult<()> {
+    return match available {
+        _ if available >= times => Ok(()),
+        0 => Err(TodoError::invalid_input(action, &format!("There is nothing to {action}"))),
+        1 => Err(TodoError::invalid_input(action, &format!("Only 1 change can be {done}"))),
+        _ => Err(TodoError::invalid_input(action, &format!("Only {available} changes can be {done}"))),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::History;
+    use crate::todo_backend::todo::{Archive, Priority, TodoList};
+
+    //adds an item and records it as one change
+    fn add(history: &mut History, todo: &mut TodoList, archive: &Archive, title: &str) -> usize {
+        history.begin(todo, archive);
+        let id = todo.add_item(title).unwrap();
+        history.commit(todo, archive);
+        return id;
+    }
+
+    fn titles(todo: &TodoList) -> Vec<&str> {
+        return todo.todo_items.iter().map(|item| item.title.as_str()).collect();
+    }
+
+    #[test]
+    fn undo_and_redo_every_part() {
+        let (mut todo, mut archive, mut history) = (TodoList::new(), Archive::default(), History::new(10));
+        let a = add(&mut history, &mut todo, &archive, "a");
+        add(&mut history, &mut todo, &archive, "b");
+        history.begin(&todo, &archive);
+        todo.set_priority(a, Priority::High).unwrap();
+        todo.complete_item(a, false).unwrap();
+        todo.delete_item(a, &mut archive).unwrap();
+        history.commit(&todo, &archive);
+        let (done_todo, done_archive) = (todo.clone(), archive.clone());
+
+        history.undo(&mut todo, &mut archive, 1).unwrap();
+        assert_eq!(titles(&todo), ["a", "b"]);
+        assert_eq!(todo.todo_items[0].priority, Priority::None);
+        assert!(archive.trash.is_empty());
+        history.undo(&mut todo, &mut archive, 2).unwrap();
+        assert!(todo == TodoList::new());
+
+        history.redo(&mut todo, &mut archive, 3).unwrap();
+        assert!(todo == done_todo && archive == done_archive);
+        //the ids handed out stay handed out
+        assert_eq!(add(&mut history, &mut todo, &archive, "c"), 3);
+    }
+
+    #[test]
+    fn undoing_more_than_there_is_changes_nothing() {
+        let (mut todo, mut archive, mut history) = (TodoList::new(), Archive::default(), History::new(10));
+        add(&mut history, &mut todo, &archive, "a");
+        add(&mut history, &mut todo, &archive, "b");
+        assert!(history.undo(&mut todo, &mut archive, 3).is_err());
+        assert_eq!(titles(&todo), ["a", "b"]);
+        assert!(history.redo(&mut todo, &mut archive, 1).is_err());
+        history.undo(&mut todo, &mut archive, 2).unwrap();
+        assert!(history.redo(&mut todo, &mut archive, 3).is_err());
+        assert!(todo.todo_items.is_empty());
+    }
+
+    #[test]
+    fn only_the_newest_changes_are_kept() {
+        let (mut todo, mut archive, mut history) = (TodoList::new(), Archive::default(), History::new(2));
+        for title in ["a", "b", "c"] {
+            add(&mut history, &mut todo, &archive, title);
+        }
+        assert!(history.undo(&mut todo, &mut archive, 3).is_err());
+        history.undo(&mut todo, &mut archive, 2).unwrap();
+        assert_eq!(titles(&todo), ["a"]);
+
+        history.redo(&mut todo, &mut archive, 2).unwrap();
+        history.set_depth(1);
+        history.undo(&mut todo, &mut archive, 1).unwrap();
+        assert!(history.undo(&mut todo, &mut archive, 1).is_err());
+        assert_eq!(titles(&todo), ["a", "b"]);
+    }
+
+    #[test]
+    fn a_new_change_clears_redo() {
+        let (mut todo, mut archive, mut history) = (TodoList::new(), Archive::default(), History::new(10));
+        add(&mut history, &mut todo, &archive, "a");
+        add(&mut history, &mut todo, &archive, "b");
+        history.undo(&mut todo, &mut archive, 1).unwrap();
+        add(&mut history, &mut todo, &archive, "c");
+        assert!(history.redo(&mut todo, &mut archive, 1).is_err());
+        assert_eq!(titles(&todo), ["a", "c"]);
+        //a change that changed nothing isn't one
+        history.begin(&todo, &archive);
+        history.commit(&todo, &archive);
+        history.undo(&mut todo, &mut archive, 1).unwrap();
+        assert_eq!(titles(&todo), ["a"]);
+    }
+
+    #[test]
+    fn steps_only_keep_what_changed() {
+        let (mut todo, archive, mut history) = (TodoList::new(), Archive::default(), History::new(10));
+        for title in ["a", "b", "c", "d"] {
+            add(&mut history, &mut todo, &archive, title);
+        }
+        history.begin(&todo, &archive);
+        todo.set_priority(3, Priority::Low).unwrap();
+        history.commit(&todo, &archive);
+        let step = history.undo.back().unwrap();
+        assert_eq!(step.splices.len(), 1);
+        assert_eq!((step.splices[0].start, step.splices[0].count), (2, 1));
+        assert_eq!(step.splices[0].items[0].title, "c");
+    }
+}
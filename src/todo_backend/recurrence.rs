@@ -0,0 +1,187 @@
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Weekday};
+use core::fmt;
+use serde::{Deserialize, Serialize};
+use super::error::{TodoError, TodoResult};
+use std::str::FromStr;
+
+///How a task repeats once it has been completed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum Recurrence {
+    Daily,
+    Weekly(Vec<Weekday>),
+    //day of the month, months without that day use their last day
+    Monthly(u32),
+    //counted from the moment the task was completed instead of from the due date
+    AfterCompletion(u32),
+}
+
+impl Recurrence {
+    ///The first due date of the next occurrence that is still in the future.
+    ///Tasks without a due date are treated as if they were due now
+    pub fn next_due(&self, due_date: Option<NaiveDateTime>, now: NaiveDateTime) -> NaiveDateTime {
+        let base = due_date.unwrap_or(now);
+        let mut next = base;
+        match self {
+            Recurrence::AfterCompletion(days) => {
+                return now.date().and_time(base.time()) + Duration::days(*days as i64);
+            }
+            Recurrence::Daily => {
+                next += Duration::days(1);
+                while next <= now {
+                    next += Duration::days(1);
+                }
+            }
+            Recurrence::Weekly(weekdays) => {
+                next += Duration::days(1);
+                while next <= now || !weekdays.contains(&next.weekday()) {
+                    next += Duration::days(1);
+                }
+            }
+            Recurrence::Monthly(day) => {
+                let (mut year, mut month) = (base.year(), base.month());
+                loop {
+                    (year, month) = match month {
+                        12 => (year + 1, 1),
+                        _ => (year, month + 1),
+                    };
+                    next = day_in_month(year, month, *day).and_time(base.time());
+                    if next > now {
+                        break;
+                    }
+                }
+            }
+        }
+        return next;
+    }
+}
+
+fn day_in_month(year: i32, month: u32, day: u32) -> NaiveDate {
+    let mut day = day.clamp(1, 31);
+    loop {
+        if let Some(date) = NaiveDate::from_ymd_opt(year, month, day) {
+            return date;
+        }
+        day -= 1;
+    }
+}
+
+fn parse_weekday(s: &str) -> Option<Weekday> {
+    return s.parse::<Weekday>().ok();
+}
+
+impl FromStr for Recurrence {
+    type Err = TodoError;
+
+    ///Accepts "daily", "weekly mon fri", "monthly 15" and "every 3 days"
+    fn from_str(s: &str) -> TodoResult<Recurrence> {
+        let invalid = || {
+            TodoError::invalid_input(s, "Expected daily, weekly <days>, monthly <day> or every <n> days")
+        };
+        let lowercase = s.to_lowercase();
+        let words: Vec<&str> = lowercase
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|word| !word.is_empty())
+            .collect();
+
+        let parse_number = |word: Option<&&str>| -> TodoResult<u32> {
+            match word.map(|word| word.parse::<u32>()) {
+                Some(Ok(r)) if r > 0 => Ok(r),
+                _ => Err(invalid()),
+            }
+        };
+
+        match words.first() {
+            Some(&"daily") if words.len() == 1 => Ok(Recurrence::Daily),
+            Some(&"weekly") if words.len() > 1 => {
+                let weekdays = words[1..]
+                    .iter()
+                    .map(|word| parse_weekday(word))
+                    .collect::<Option<Vec<Weekday>>>()
+                    .ok_or_else(invalid)?;
+                Ok(Recurrence::Weekly(weekdays))
+            }
+            Some(&"monthly") if words.len() == 2 => {
+                let day = parse_number(words.get(1))?;
+                if day > 31 {
+                    return Err(invalid());
+                }
+                Ok(Recurrence::Monthly(day))
+            }
+            Some(&"every") if words.len() == 3 && words[2].starts_with("day") => {
+                Ok(Recurrence::AfterCompletion(parse_number(words.get(1))?))
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Recurrence::Daily => write!(f, "daily"),
+            Recurrence::Weekly(weekdays) => {
+                let weekdays: Vec<String> = weekdays
+                    .iter()
+                    .map(|weekday| weekday.to_string().to_lowercase())
+                    .collect();
+                write!(f, "weekly {}", weekdays.join(" "))
+            }
+            Recurrence::Monthly(day) => write!(f, "monthly {day}"),
+            Recurrence::AfterCompletion(days) => write!(f, "every {days} days"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Recurrence;
+    use chrono::{NaiveDate, NaiveDateTime, Weekday};
+
+    fn at(year: i32, month: u32, day: u32, hour: u32) -> NaiveDateTime {
+        return NaiveDate::from_ymd_opt(year, month, day).unwrap().and_hms_opt(hour, 0, 0).unwrap();
+    }
+
+    #[test]
+    fn monthly_uses_the_last_day_of_short_months() {
+        let end_of_month = Recurrence::Monthly(31);
+        let now = at(2023, 1, 1, 0);
+        assert_eq!(end_of_month.next_due(Some(at(2023, 1, 31, 9)), now), at(2023, 2, 28, 9));
+        //the day stays 31 after a short month
+        assert_eq!(end_of_month.next_due(Some(at(2023, 2, 28, 9)), now), at(2023, 3, 31, 9));
+        assert_eq!(end_of_month.next_due(Some(at(2024, 1, 31, 9)), now), at(2024, 2, 29, 9));
+        assert_eq!(Recurrence::Monthly(15).next_due(Some(at(2023, 12, 15, 9)), now), at(2024, 1, 15, 9));
+        //months that passed are skipped
+        assert_eq!(end_of_month.next_due(Some(at(2022, 10, 31, 9)), at(2023, 4, 30, 12)), at(2023, 5, 31, 9));
+    }
+
+    #[test]
+    fn weekly_goes_to_the_next_chosen_weekday() {
+        let mon_fri = Recurrence::Weekly(vec![Weekday::Mon, Weekday::Fri]);
+        let now = at(2023, 5, 1, 0);
+        //2023-05-15 is a Monday
+        assert_eq!(mon_fri.next_due(Some(at(2023, 5, 15, 9)), now), at(2023, 5, 19, 9));
+        assert_eq!(mon_fri.next_due(Some(at(2023, 5, 19, 9)), now), at(2023, 5, 22, 9));
+        assert_eq!(mon_fri.next_due(Some(at(2023, 5, 1, 9)), at(2023, 5, 16, 12)), at(2023, 5, 19, 9));
+        assert_eq!(Recurrence::Daily.next_due(Some(at(2023, 5, 1, 9)), at(2023, 5, 16, 12)), at(2023, 5, 17, 9));
+    }
+
+    #[test]
+    fn after_completion_counts_from_now() {
+        let every_3_days = Recurrence::AfterCompletion(3);
+        let now = at(2023, 5, 12, 17);
+        //the time of day is kept
+        assert_eq!(every_3_days.next_due(Some(at(2023, 5, 1, 9)), now), at(2023, 5, 15, 9));
+        assert_eq!(every_3_days.next_due(Some(at(2023, 6, 1, 9)), now), at(2023, 5, 15, 9));
+        assert_eq!(every_3_days.next_due(None, now), at(2023, 5, 15, 17));
+    }
+
+    #[test]
+    fn reads_what_it_writes() {
+        for text in ["daily", "weekly mon fri", "monthly 31", "every 3 days"] {
+            assert_eq!(text.parse::<Recurrence>().unwrap().to_string(), text);
+        }
+        for text in ["weekly", "monthly 32", "monthly 0", "every 0 days", "every day"] {
+            assert!(text.parse::<Recurrence>().is_err(), "{text}");
+        }
+    }
+}
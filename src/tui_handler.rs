@@ -3,13 +3,16 @@ mod tui_input_handler;
 mod tui_rendering_handler;
 
 pub mod tui_handler {
-    use crate::todo_backend::todo::TodoList;
+    use crate::todo_backend::todo::{
+        compare_items, Archive, Filter, History, Priority, Shelf, TodoError, TodoItem, TodoList, TodoResult,
+    };
+    use crate::config_handler::config_handler::{Config, KeyMap};
+    use chrono::NaiveDateTime;
     use crate::tui_handler::{
         tui_buffer_handler as buffer,
         tui_input_handler as input,
         tui_rendering_handler as render,
     };
-    use crate::parsing_handler;
     use crossterm::event as CEvent;
     use crossterm::execute;
     use crossterm::terminal::{
@@ -17,27 +20,247 @@ pub mod tui_handler {
     };
     use std::convert::From;
     use std::io::stdout;
-    use std::io::ErrorKind;
     use std::io::Stdout;
+    use crate::data_handler::data_handler::{archive_path, Store};
+    use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+    use signal_hook::consts::{SIGHUP, SIGTERM};
+    use signal_hook::iterator::Signals;
+    use std::sync::atomic::{AtomicBool, Ordering};
     use std::sync::{Arc, Mutex};
     use std::{
         io,
         sync::mpsc::channel,
+        ffi::OsString,
+        path::{Path, PathBuf},
         sync::mpsc::Receiver,
+        sync::mpsc::Sender,
         thread,
         time::{Duration, Instant},
     };
     use tui::backend::CrosstermBackend;
+    use tui::text::{Span, Spans};
     use tui::Terminal;
 
     pub const MAX_TICK_TIME: Duration = Duration::from_millis(200);
     const COMPLETED_ITEM: [char; 2] = [' ', '✓'];
+    //how far PgUp and PgDn move the selection
+    const PAGE_SIZE: usize = 10;
 
     type ResultIo<T> = Result<T, io::Error>;
 
+    ///The list being worked on plus the view of it that the TUI keeps between inputs
+    pub struct TuiData<'a> {
+        pub todo: &'a mut TodoList,
+        pub archive: &'a mut Archive,
+        pub history: &'a mut History,
+        pub filter: Filter,
+        pub todo_items: render::TodoItems,
+        //the fields of the item being edited
+        pub edit: Option<EditForm>,
+        //the named lists shown in the list picker
+        pub lists: Vec<String>,
+        pub current_list: String,
+        //set when another list was picked, the TUI closes so it can be opened
+        pub switch_to: Option<String>,
+        pub keys: &'a KeyMap,
+        //where the list went when it couldn't be saved while closing
+        pub conflict_copy: Option<PathBuf>,
+        store: &'a Store,
+        //what was last written to disk, autosave only saves when the list differs from it
+        saved: (TodoList, Archive),
+    }
+    impl<'a> TuiData<'a> {
+        pub fn new(
+            todo: &'a mut TodoList,
+            archive: &'a mut Archive,
+            history: &'a mut History,
+            current_list: &str,
+            lists: Vec<String>,
+            keys: &'a KeyMap,
+            store: &'a Store,
+        ) -> TuiData<'a> {
+            let filter = Filter::default();
+            let saved = (todo.clone(), archive.clone());
+            let mut todo_items = generate_todo(todo, &filter);
+            todo_items.selected = todo_items.selectable().first().copied();
+            TuiData {
+                todo,
+                archive,
+                history,
+                filter,
+                todo_items,
+                edit: None,
+                lists,
+                current_list: current_list.to_owned(),
+                switch_to: None,
+                keys,
+                conflict_copy: None,
+                store,
+                saved,
+            }
+        }
+        ///Saves the list if it changed since the last save
+        pub fn autosave(&mut self) -> TodoResult<()> {
+            if *self.todo == self.saved.0 && *self.archive == self.saved.1 {
+                return Ok(());
+            }
+            self.store.save(self.todo, self.archive, self.history)?;
+            self.saved = (self.todo.clone(), self.archive.clone());
+            return Ok(());
+        }
+        ///Keeps the list in a conflict copy and loads the one on disk, so nothing is left to save over it.
+        ///Undo in the next session brings back the copied list when the undo history is kept
+        pub fn keep_conflict_copy(&mut self, current_state: &State) -> TodoResult<()> {
+            self.conflict_copy = Some(self.store.keep_conflict_copy(self.todo, self.archive)?);
+            return self.reload(current_state);
+        }
+        ///Replaces the list with the one another program saved, undo brings back the replaced one
+        pub fn reload(&mut self, current_state: &State) -> TodoResult<()> {
+            let (todo, archive) = self.store.load()?;
+            self.history.begin(self.todo, self.archive);
+            *self.todo = todo;
+            *self.archive = archive;
+            self.history.commit(self.todo, self.archive);
+            self.saved = (self.todo.clone(), self.archive.clone());
+            self.refresh(current_state);
+            return Ok(());
+        }
+        ///Reloads the list if another program changed it, unless that would drop unsaved changes,
+        ///those get saved or asked about on the next tick. Returns whether it was reloaded
+        pub fn reload_if_changed(&mut self, current_state: &State) -> TodoResult<bool> {
+            let unsaved = *self.todo != self.saved.0 || *self.archive != self.saved.1;
+            if unsaved || !self.store.changed_on_disk()? {
+                return Ok(false);
+            }
+            self.reload(current_state)?;
+            return Ok(true);
+        }
+        ///Saves the list over the one another program saved
+        pub fn overwrite(&mut self) -> TodoResult<()> {
+            self.store.overwrite(self.todo, self.archive, self.history)?;
+            self.saved = (self.todo.clone(), self.archive.clone());
+            return Ok(());
+        }
+        ///Regenerates the items shown in the middle of the screen for the current state
+        pub fn refresh(&mut self, current_state: &State) {
+            let todo_items = match current_state.shelf() {
+                Some(shelf) => generate_archive(self.archive, shelf, ""),
+                None if current_state.picking_list() => generate_lists(&self.lists, &self.current_list),
+                None if matches!(current_state, State::ViewingKeys) => generate_keys(self.keys),
+                None => generate_todo(self.todo, &self.filter),
+            };
+            self.set_items(todo_items);
+        }
+        ///Shows the named lists with the current one selected
+        pub fn open_list_picker(&mut self, current_state: &mut State) {
+            *current_state = State::PickingList;
+            self.todo_items = generate_lists(&self.lists, &self.current_list);
+            let current = self.lists.iter().position(|name| *name == self.current_list);
+            self.todo_items.selected = current.map(|index| (0, index));
+        }
+        ///Closes the TUI to open the list, unless it is the one already open
+        pub fn switch_list(&mut self, current_state: &mut State, name: &str) {
+            match name == self.current_list {
+                true => {
+                    *current_state = State::Viewing;
+                    self.refresh(current_state);
+                }
+                false => {
+                    self.switch_to = Some(name.to_owned());
+                    *current_state = State::Quitting;
+                }
+            }
+        }
+        ///Replaces the shown items, keeping the same item selected if it is still there
+        ///and otherwise the item that took its place
+        pub fn set_items(&mut self, mut todo_items: render::TodoItems) {
+            todo_items.keep_scroll(&self.todo_items);
+            let previous = self.todo_items.selected.unwrap_or((0, 0));
+            let selectable = todo_items.selectable();
+            todo_items.selected = self
+                .todo_items
+                .selected_id()
+                .and_then(|id| todo_items.position_of(id))
+                .or_else(|| selectable.iter().copied().find(|index| *index >= previous))
+                .or_else(|| selectable.last().copied());
+            self.todo_items = todo_items;
+        }
+        pub fn selected_id(&self) -> Option<usize> {
+            return self.todo_items.selected_id();
+        }
+        ///Fills the edit form with the item's current fields, returns what goes into the buffer
+        pub fn start_edit(&mut self, item_id: usize) -> TodoResult<String> {
+            let item = match self.todo.find_item_mut(item_id) {
+                Some(r) => r,
+                None => return Err(TodoError::UnknownId(item_id)),
+            };
+            let form = EditForm::new(item);
+            let value = form.value().to_owned();
+            self.edit = Some(form);
+            return Ok(value);
+        }
+        pub fn move_selection(&mut self, movement: Movement) {
+            let selectable = self.todo_items.selectable();
+            if selectable.is_empty() {
+                return;
+            }
+            let last = selectable.len() - 1;
+            let current = self
+                .todo_items
+                .selected
+                .and_then(|selected| selectable.iter().position(|index| *index == selected))
+                .unwrap_or(0);
+            let next = match movement {
+                Movement::Up => current.saturating_sub(1),
+                Movement::Down => (current + 1).min(last),
+                Movement::PageUp => current.saturating_sub(PAGE_SIZE),
+                Movement::PageDown => (current + PAGE_SIZE).min(last),
+                Movement::Top => 0,
+                Movement::Bottom => last,
+                //the first visible row of the next section, wrapping back to the first section
+                Movement::NextSection => {
+                    let (section, _) = selectable[current];
+                    let sections = &self.todo_items.sections;
+                    let next_section = (1..=sections.len())
+                        .map(|step| (section + step) % sections.len())
+                        .find(|index| !sections[*index].rows.is_empty())
+                        .unwrap_or(section);
+                    let row = sections[next_section].offset().min(sections[next_section].rows.len() - 1);
+                    selectable.iter().position(|index| *index == (next_section, row)).unwrap_or(current)
+                }
+            };
+            self.todo_items.selected = Some(selectable[next]);
+        }
+        ///Applies action to the selected item as one undoable change,
+        ///without a selected item the user is asked for an id in the prompt state instead
+        pub fn act_on_selected(
+            &mut self,
+            current_state: &mut State,
+            prompt: State,
+            action: impl FnOnce(&mut TodoList, &mut Archive, usize) -> TodoResult<()>,
+        ) -> TodoResult<()> {
+            let item_id = match self.selected_id() {
+                Some(r) => r,
+                None => {
+                    *current_state = prompt;
+                    return Ok(());
+                }
+            };
+            self.history.begin(self.todo, self.archive);
+            let result = action(self.todo, self.archive, item_id);
+            self.history.commit(self.todo, self.archive);
+            self.refresh(current_state);
+            return result;
+        }
+    }
+
     pub enum Event<T> {
         Input(T),
         Tick,
+        //the list or its archive changed on disk, including through our own saves
+        FileChanged,
+        //SIGTERM or SIGHUP arrived, or the terminal can't be read anymore
+        Terminated,
     }
 
     pub enum State {
@@ -48,7 +271,39 @@ pub mod tui_handler {
         AddingTodo,
         CompletingTodo,
         UncompletingTodo,
-        Error,
+        SettingPriority,
+        SettingRecurrence,
+        Filtering,
+        AddingSubtask,
+        EnteringItemId(ItemPrompt),
+        EditingTodo(usize),
+        //the archive view shows either the archive or the trash
+        ViewingArchive(Shelf),
+        SearchingArchive(Shelf),
+        RestoringArchived(Shelf),
+        //waits for y before the items are moved into the trash
+        ConfirmingDelete(Vec<usize>),
+        PickingList,
+        NamingList,
+        //lists the active key bindings
+        ViewingKeys,
+        //another program saved the list, waits for r to reload it or o to overwrite it.
+        //true when the TUI quits once that is decided
+        ConfirmingReload(bool),
+    }
+    impl State {
+        ///The shelf shown while in the archive view
+        pub fn shelf(&self) -> Option<Shelf> {
+            return match self {
+                State::ViewingArchive(shelf)
+                | State::SearchingArchive(shelf)
+                | State::RestoringArchived(shelf) => Some(*shelf),
+                _ => None,
+            };
+        }
+        pub fn picking_list(&self) -> bool {
+            return matches!(self, State::PickingList | State::NamingList);
+        }
     }
 
     pub enum UserAction {
@@ -58,15 +313,171 @@ pub mod tui_handler {
         AddTodo,
         CompeleteTodo,
         UncompleteTodo,
+        SetPriority,
+        SetRecurrence,
+        Filter,
+        AddSubtask,
+        PromptItem(ItemPrompt),
+        ShowArchive(Shelf),
+        CloseArchive,
+        SearchArchive,
+        RestoreArchived,
+        Undo,
+        Redo,
+        ConfirmDelete(bool),
+        //true reloads the list, false overwrites it
+        ConfirmReload(bool),
+        MoveSelection(Movement),
+        DeleteSelected,
+        EditSelected,
+        PrioritizeSelected(Priority),
+        PickList,
+        ShowKeys,
+        OpenSelectedList,
+        NewList,
+        CloseView,
         ManipulateBuffer(BufferAction),
         None,
     }
 
+    pub enum Movement {
+        Up,
+        Down,
+        PageUp,
+        PageDown,
+        Top,
+        Bottom,
+        NextSection,
+    }
+
     pub enum BufferAction {
         Input(char),
         SubmitBuffer,
         Backspace,
         ExitBuffer,
+        NextField,
+        PreviousField,
+    }
+
+    #[derive(Copy, Clone, PartialEq)]
+    pub enum EditField {
+        Title,
+        Due,
+        Priority,
+        Recurrence,
+        Project,
+        Tags,
+    }
+    impl EditField {
+        pub fn label(&self) -> &'static str {
+            match self {
+                EditField::Title => "Title",
+                EditField::Due => "Due",
+                EditField::Priority => "Priority",
+                EditField::Recurrence => "Repeats",
+                EditField::Project => "Project",
+                EditField::Tags => "Tags",
+            }
+        }
+        ///The key the field has in an edit command
+        fn key(&self) -> &'static str {
+            match self {
+                EditField::Title => "title",
+                EditField::Due => "due",
+                EditField::Priority => "prio",
+                EditField::Recurrence => "every",
+                EditField::Project => "project",
+                EditField::Tags => "tags",
+            }
+        }
+    }
+
+    ///Every editable field of an item, starting out with the item's current values.
+    ///The buffer holds the field being typed in, the rest are kept here
+    pub struct EditForm {
+        pub item_id: usize,
+        pub fields: Vec<(EditField, String)>,
+        originals: Vec<String>,
+        pub current: usize,
+    }
+    impl EditForm {
+        fn new(item: &TodoItem) -> EditForm {
+            let fields = vec![
+                (EditField::Title, item.title.clone()),
+                (EditField::Due, item.due_date.map_or(String::new(), |due| due.format("%Y-%m-%d %H:%M").to_string())),
+                (EditField::Priority, item.priority.to_string()),
+                (EditField::Recurrence, item.recurrence.as_ref().map_or(String::new(), |rule| rule.to_string())),
+                (EditField::Project, item.project.clone().unwrap_or_default()),
+                (EditField::Tags, item.tags.join(",")),
+            ];
+            let originals = fields.iter().map(|(_, value)| value.clone()).collect();
+            EditForm { item_id: item.id, fields, originals, current: 0 }
+        }
+        pub fn value(&self) -> &str {
+            return &self.fields[self.current].1;
+        }
+        ///Keeps what was typed for the current field and moves to the next (or previous) one,
+        ///returns the value of the field moved to
+        pub fn switch_field(&mut self, buffer: &str, forward: bool) -> String {
+            self.fields[self.current].1 = buffer.to_owned();
+            let len = self.fields.len();
+            self.current = match forward {
+                true => (self.current + 1) % len,
+                false => (self.current + len - 1) % len,
+            };
+            return self.value().to_owned();
+        }
+        ///An edit command with only the changed fields, None when nothing changed.
+        ///Fields that were cleared are set to none
+        pub fn to_command(&self) -> TodoResult<Option<String>> {
+            let mut command = format!("edit {}", self.item_id);
+            let mut changed = false;
+            for ((field, value), original) in self.fields.iter().zip(&self.originals) {
+                let value = value.trim();
+                if value == original {
+                    continue;
+                }
+                changed = true;
+                match (field, value.is_empty()) {
+                    (EditField::Title, true) => {
+                        return Err(TodoError::invalid_input(value, "Titles can't be empty"));
+                    }
+                    (_, true) => command += &format!(" {}:none", field.key()),
+                    (_, false) => {
+                        let escaped = value.replace('\\', "\\\\").replace('"', "\\\"");
+                        command += &format!(" {}:\"{escaped}\"", field.key());
+                    }
+                }
+            }
+            return Ok(changed.then_some(command));
+        }
+    }
+
+    ///Actions on a single item that only need its id typed into the buffer
+    #[derive(Copy, Clone)]
+    pub enum ItemPrompt {
+        CompleteWithSubtasks,
+        Indent,
+        Outdent,
+        ToggleCollapse,
+    }
+    impl ItemPrompt {
+        pub fn label(&self) -> &'static str {
+            match self {
+                ItemPrompt::CompleteWithSubtasks => "CompletingWithSubtasks",
+                ItemPrompt::Indent => "Indenting",
+                ItemPrompt::Outdent => "Outdenting",
+                ItemPrompt::ToggleCollapse => "Collapsing/Expanding",
+            }
+        }
+        pub fn apply(&self, todo: &mut TodoList, item_id: usize) -> TodoResult<()> {
+            match self {
+                ItemPrompt::CompleteWithSubtasks => todo.complete_item(item_id, true),
+                ItemPrompt::Indent => todo.indent_item(item_id),
+                ItemPrompt::Outdent => todo.outdent_item(item_id),
+                ItemPrompt::ToggleCollapse => todo.toggle_collapsed(item_id),
+            }
+        }
     }
 
     #[derive(Copy, Clone)]
@@ -93,7 +504,19 @@ pub mod tui_handler {
         }
     }
 
-    pub fn run_tui(todo_list: &mut TodoList) -> ResultIo<()> {
+    ///Returns the name of the list picked in the list picker, if another one was picked
+    pub fn run_tui(
+        todo_list: &mut TodoList,
+        archive: &mut Archive,
+        history: &mut History,
+        current_list: &str,
+        lists: Vec<String>,
+        config: &Config,
+        store: &Store,
+    ) -> ResultIo<Option<String>> {
+        render::set_theme(config.theme);
+        let mut signals = Signals::new([SIGTERM, SIGHUP])?;
+        let signals_handle = signals.handle();
         enable_raw_mode().expect("Raw Mode");
         execute!(stdout(), cTerm::EnterAlternateScreen).unwrap();
 
@@ -101,55 +524,94 @@ pub mod tui_handler {
 
         let (sx, rx) = channel();
         let mut threads = Vec::new();
+        //without a watcher changes from other programs are only noticed when saving
+        let _watcher = watch_file(&store.file, sx.clone()).ok();
 
-        //input thread and loop
+        //signal thread, it ends once the handle is closed
         {
-            let current_state = current_state.clone();
+            let sx = sx.clone();
+            threads.push(thread::spawn(move || {
+                for _ in signals.forever() {
+                    let _ = sx.send(Event::Terminated);
+                }
+            }));
+        }
+
+        //input thread and loop, it runs until the TUI is closed so a prompt while quitting can be answered
+        let stop_input = Arc::new(AtomicBool::new(false));
+        {
+            let stop_input = stop_input.clone();
 
             threads.push(thread::spawn(move || {
                 let mut current_tick_time = Instant::now();
-                loop {
-                    let current_state = current_state.lock().unwrap();
-
-                    if let State::Quitting = *current_state {
+                while !stop_input.load(Ordering::Relaxed) {
+                    //a closed terminal is treated like a hangup
+                    if input::capture_input(&sx, &mut current_tick_time).is_err() {
+                        let _ = sx.send(Event::Terminated);
                         break;
                     }
-                    drop(current_state);
-
-                    input::capture_input(&sx, &mut current_tick_time)
-                        .expect("Input handler crashed");
                 }
             }));
         }
 
-        let tui_result = tui_loop(&rx, &current_state, todo_list);
-
-        {
-            let mut current_state = current_state.lock().unwrap();
-            *current_state = State::Quitting;
-        }
+        let mut data = TuiData::new(todo_list, archive, history, current_list, lists, &config.keys, store);
+        let tui_result = tui_loop(&rx, &current_state, &mut data);
 
-        //exits gracefully on error
-        threads
-            .into_iter()
-            .for_each(|thread| thread.join().unwrap());
+        stop_input.store(true, Ordering::Relaxed);
+        signals_handle.close();
+        //a thread that panicked has nothing left to clean up, the list still gets saved
+        threads.into_iter().for_each(|thread| {
+            let _ = thread.join();
+        });
 
-        disable_raw_mode().unwrap();
-        execute!(stdout(), LeaveAlternateScreen).unwrap();
+        //the terminal may already be gone after a SIGHUP
+        let _ = disable_raw_mode();
+        let _ = execute!(stdout(), LeaveAlternateScreen);
 
-        if let Err(e) = tui_result {
-            eprintln!("{:?}", e);
+        if let Some(copy) = &data.conflict_copy {
+            eprintln!(
+                "'{}' was changed by another program while closing, the list was saved to '{}' instead",
+                store.file.display(),
+                copy.display()
+            );
         }
 
-        return Ok(());
+        return match tui_result {
+            Ok(switch_to) => Ok(switch_to),
+            Err(e) => {
+                eprintln!("{:?}", e);
+                Ok(None)
+            }
+        };
+    }
+
+    ///Sends FileChanged whenever the list or its archive changes. The directory is watched
+    ///since saving renames a new file over the old one
+    fn watch_file(file: &Path, sx: Sender<Event<CEvent::KeyEvent>>) -> notify::Result<RecommendedWatcher> {
+        let watched: Vec<OsString> = [file.to_path_buf(), archive_path(file)]
+            .iter()
+            .filter_map(|path| path.file_name().map(|name| name.to_owned()))
+            .collect();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            let is_watched = |path: &PathBuf| path.file_name().is_some_and(|name| watched.iter().any(|r| r == name));
+            let changed = event.is_ok_and(|event| !event.kind.is_access() && event.paths.iter().any(is_watched));
+            if changed {
+                let _ = sx.send(Event::FileChanged);
+            }
+        })?;
+        let dir = match file.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        return Ok(watcher);
     }
 
     fn tui_loop(
         rx: &Receiver<Event<CEvent::KeyEvent>>,
         current_state: &Arc<Mutex<State>>,
-        todo: &mut TodoList,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let mut todo_items = generate_todo(todo);
+        data: &mut TuiData,
+    ) -> Result<Option<String>, Box<dyn std::error::Error>> {
 
         let stdout = io::stdout();
         let backend = CrosstermBackend::new(stdout);
@@ -158,20 +620,96 @@ pub mod tui_handler {
         let mut user_input_buffer = String::new();
         let mut name_storage_buff = String::new();
         let mut date_storage_buff = String::new();
+        let mut terminated = false;
 
-        render::render_main(&mut terminal, render::BufferType::None, &todo_items)?;
+        render::render_main(&mut terminal, render::BufferType::None, &data.todo_items)?;
         loop {
             {
-                let current_state = current_state.lock().unwrap();
+                let mut current_state = current_state.lock().unwrap();
                 if let State::Quitting = *current_state {
-                    return Ok(());
+                    match data.autosave() {
+                        Ok(()) => return Ok(data.switch_to.take()),
+                        //asks before losing either version, unless nobody is left to ask
+                        Err(TodoError::Conflict(_)) if !terminated => {
+                            *current_state = State::ConfirmingReload(true);
+                            render(
+                                &mut current_state,
+                                &user_input_buffer,
+                                &data.todo_items,
+                                &mut terminal,
+                                &name_storage_buff,
+                                &date_storage_buff,
+                                data.edit.as_ref(),
+                            )?;
+                        }
+                        //nobody can answer the prompt, the list is kept next to the file instead
+                        Err(TodoError::Conflict(_)) => {
+                            data.keep_conflict_copy(&current_state)?;
+                            return Ok(data.switch_to.take());
+                        }
+                        Err(e) => return Err(e.into()),
+                    }
                 }
             }
 
-            //waits for user-input to render
+            //waits for user-input to render, changes are saved on the tick after them
             let input_result = match rx.recv()? {
-                Event::Input(input) => input::handle_input(input, &current_state),
-                Event::Tick => continue,
+                Event::Input(input) => input::handle_input(input, current_state, data.keys),
+                Event::Tick => {
+                    let mut current_state = current_state.lock().unwrap();
+                    if let State::ConfirmingReload(_) = *current_state {
+                        continue;
+                    }
+                    match data.autosave() {
+                        Ok(()) => {}
+                        Err(TodoError::Conflict(_)) => {
+                            *current_state = State::ConfirmingReload(false);
+                            render(
+                                &mut current_state,
+                                &user_input_buffer,
+                                &data.todo_items,
+                                &mut terminal,
+                                &name_storage_buff,
+                                &date_storage_buff,
+                                data.edit.as_ref(),
+                            )?;
+                        }
+                        Err(e) => handle_errors(e, &mut terminal, &data.todo_items)?,
+                    }
+                    continue;
+                }
+                //saves and quits like q, without asking anything
+                Event::Terminated => {
+                    terminated = true;
+                    *current_state.lock().unwrap() = State::Quitting;
+                    continue;
+                }
+                //the state and whatever is being typed stay as they are
+                Event::FileChanged => {
+                    let mut current_state = current_state.lock().unwrap();
+                    if let State::ConfirmingReload(_) | State::Quitting = *current_state {
+                        continue;
+                    }
+                    match data.reload_if_changed(&current_state) {
+                        Ok(true) => render(
+                            &mut current_state,
+                            &user_input_buffer,
+                            &data.todo_items,
+                            &mut terminal,
+                            &name_storage_buff,
+                            &date_storage_buff,
+                            data.edit.as_ref(),
+                        )?,
+                        Ok(false) => {}
+                        //a file caught while another program writes it is read again on its next change
+                        Err(e) => render::render_main(
+                            &mut terminal,
+                            render::BufferType::Error(&e.to_string()),
+                            &data.todo_items,
+                        )?,
+                    }
+                    continue;
+                }
             };
 
             //semaphore for inputs
@@ -180,51 +718,211 @@ pub mod tui_handler {
             let input_result = match input_result {
                 Ok(result) => result,
                 Err(e) => {
-                    handle_errors(e, &mut terminal, &todo_items)?;
+                    handle_errors(e, &mut terminal, &data.todo_items)?;
                     continue;
                 }
             };
 
-            match input_result {
+            //single key actions work on the selected item, the prompts are for when nothing is selected
+            let action_result = match input_result {
                 //just change the state depending on user action
-                UserAction::Command => *current_state = State::EnteringCommand,
-                UserAction::Quit => *current_state = State::Quitting,
-                UserAction::AddTodo => *current_state = State::AddingTodo,
-                UserAction::AddTodoDate => *current_state = State::AddingTodoDate(AddState::EnteringName),
-                UserAction::CompeleteTodo => *current_state = State::CompletingTodo,
-                UserAction::UncompleteTodo => *current_state = State::UncompletingTodo,
+                UserAction::Command => {
+                    *current_state = State::EnteringCommand;
+                    Ok(())
+                }
+                UserAction::Quit => {
+                    *current_state = State::Quitting;
+                    Ok(())
+                }
+                UserAction::AddTodo => {
+                    *current_state = State::AddingTodo;
+                    Ok(())
+                }
+                UserAction::AddTodoDate => {
+                    *current_state = State::AddingTodoDate(AddState::EnteringName);
+                    Ok(())
+                }
+                UserAction::CompeleteTodo => data.act_on_selected(
+                    &mut current_state,
+                    State::CompletingTodo,
+                    |todo, _, id| todo.complete_item(id, false),
+                ),
+                UserAction::UncompleteTodo => data.act_on_selected(
+                    &mut current_state,
+                    State::UncompletingTodo,
+                    |todo, _, id| todo.uncomplete_item(id),
+                ),
+                UserAction::PromptItem(prompt) => data.act_on_selected(
+                    &mut current_state,
+                    State::EnteringItemId(prompt),
+                    |todo, _, id| prompt.apply(todo, id),
+                ),
+                UserAction::RestoreArchived => {
+                    let shelf = current_state.shelf().unwrap_or(Shelf::Archive);
+                    data.act_on_selected(
+                        &mut current_state,
+                        State::RestoringArchived(shelf),
+                        |todo, archive, id| match shelf {
+                            Shelf::Archive => todo.restore_archived(id, archive),
+                            Shelf::Trash => todo.restore_deleted(id, archive),
+                        },
+                    )
+                }
+                //deleting always asks first
+                UserAction::DeleteSelected => {
+                    match data.selected_id() {
+                        Some(id) => *current_state = State::ConfirmingDelete(vec![id]),
+                        None => {
+                            *current_state = State::EnteringCommand;
+                            user_input_buffer = String::from("rm ");
+                        }
+                    }
+                    Ok(())
+                }
+                UserAction::ConfirmDelete(confirmed) => {
+                    if let (true, State::ConfirmingDelete(item_ids)) = (confirmed, &*current_state) {
+                        data.history.begin(data.todo, data.archive);
+                        data.todo.delete_items(item_ids, data.archive);
+                        data.history.commit(data.todo, data.archive);
+                    }
+                    *current_state = State::Viewing;
+                    data.refresh(&current_state);
+                    Ok(())
+                }
+                UserAction::ConfirmReload(reload) => {
+                    let quit = matches!(*current_state, State::ConfirmingReload(true));
+                    *current_state = match quit {
+                        true => State::Quitting,
+                        false => State::Viewing,
+                    };
+                    match reload {
+                        true => data.reload(&current_state),
+                        false => data.overwrite(),
+                    }
+                }
+                UserAction::PrioritizeSelected(priority) => data.act_on_selected(
+                    &mut current_state,
+                    State::Viewing,
+                    |todo, _, id| todo.set_priority(id, priority),
+                ),
+                //the edit form starts out with the selected item's fields
+                UserAction::EditSelected => match data.selected_id() {
+                    Some(id) => data.start_edit(id).map(|title| {
+                        *current_state = State::EditingTodo(id);
+                        user_input_buffer = title;
+                    }),
+                    None => {
+                        *current_state = State::EnteringCommand;
+                        user_input_buffer = String::from("edit ");
+                        Ok(())
+                    }
+                },
+                //these need more than the id, so the buffer starts out with the selected id
+                UserAction::SetPriority | UserAction::SetRecurrence | UserAction::AddSubtask => {
+                    *current_state = match input_result {
+                        UserAction::SetPriority => State::SettingPriority,
+                        UserAction::SetRecurrence => State::SettingRecurrence,
+                        _ => State::AddingSubtask,
+                    };
+                    user_input_buffer = match data.selected_id() {
+                        Some(id) => format!("{id} "),
+                        None => String::new(),
+                    };
+                    Ok(())
+                }
+                UserAction::Filter => {
+                    *current_state = State::Filtering;
+                    Ok(())
+                }
+                UserAction::ShowArchive(shelf) => {
+                    *current_state = State::ViewingArchive(shelf);
+                    data.refresh(&current_state);
+                    Ok(())
+                }
+                UserAction::CloseArchive => {
+                    *current_state = State::Viewing;
+                    data.refresh(&current_state);
+                    Ok(())
+                }
+                UserAction::SearchArchive => {
+                    *current_state = State::SearchingArchive(current_state.shelf().unwrap_or(Shelf::Archive));
+                    Ok(())
+                }
+                UserAction::PickList => {
+                    data.open_list_picker(&mut current_state);
+                    Ok(())
+                }
+                UserAction::OpenSelectedList => {
+                    let picked = data.selected_id().and_then(|index| data.lists.get(index)).cloned();
+                    if let Some(name) = picked {
+                        data.switch_list(&mut current_state, &name);
+                    }
+                    Ok(())
+                }
+                UserAction::ShowKeys => {
+                    *current_state = State::ViewingKeys;
+                    data.refresh(&current_state);
+                    Ok(())
+                }
+                UserAction::NewList => {
+                    *current_state = State::NamingList;
+                    Ok(())
+                }
+                UserAction::CloseView => {
+                    *current_state = State::Viewing;
+                    data.refresh(&current_state);
+                    Ok(())
+                }
+                UserAction::MoveSelection(movement) => {
+                    data.move_selection(movement);
+                    Ok(())
+                }
+                UserAction::Undo | UserAction::Redo => {
+                    let result = match input_result {
+                        UserAction::Undo => data.history.undo(data.todo, data.archive, 1),
+                        _ => data.history.redo(data.todo, data.archive, 1),
+                    };
+                    data.refresh(&current_state);
+                    result
+                }
                 UserAction::None => continue,
                 UserAction::ManipulateBuffer(action) => {
                     let input_result = buffer::manipulate_buffer(
-                        &mut *current_state,
+                        &mut current_state,
                         action,
                         &mut user_input_buffer,
                         &mut name_storage_buff,
                         &mut date_storage_buff,
-                        todo,
-                        &mut todo_items,
+                        data,
                     );
 
                     match input_result {
                         Ok(()) => {}
                         Err(e) => {
-                            handle_errors(e, &mut terminal, &todo_items)?;
+                            handle_errors(e, &mut terminal, &data.todo_items)?;
                             *current_state = State::Viewing;
+                            data.refresh(&current_state);
                             user_input_buffer = String::new();
                             date_storage_buff = String::new();
                             continue;
                         }
                     }
+                    Ok(())
                 }
+            };
+            if let Err(e) = action_result {
+                handle_errors(e, &mut terminal, &data.todo_items)?;
+                continue;
             }
             //render the correct state
             render(
                 &mut current_state,
                 &user_input_buffer,
-                &todo_items,
+                &data.todo_items,
                 &mut terminal,
                 &name_storage_buff,
-                &*date_storage_buff,
+                &date_storage_buff,
+                data.edit.as_ref(),
             )?;
         }
     }
@@ -233,55 +931,130 @@ pub mod tui_handler {
         current_state: &mut std::sync::MutexGuard<State>,
         user_input_buffer: &String,
         todo_items: &render::TodoItems,
-        mut terminal: &mut Terminal<CrosstermBackend<Stdout>>,
-        storage_buff: &String,
+        terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+        storage_buff: &str,
         date_storage_buff: &str,
+        edit_form: Option<&EditForm>,
     ) -> ResultIo<()> {
         match **current_state {
+            State::EditingTodo(item_id) => {
+                let mut form_text = format!("Task {item_id}\n");
+                if let Some(form) = edit_form {
+                    for (index, (field, value)) in form.fields.iter().enumerate() {
+                        form_text += &match index == form.current {
+                            true => format!("\n> {}: {user_input_buffer}█", field.label()),
+                            false => format!("\n{}: {value}", field.label()),
+                        };
+                    }
+                }
+                render::render_editing(terminal, &form_text, todo_items)?
+            }
             State::Viewing => {
-                render::render_main(&mut terminal, render::BufferType::None, todo_items)?
+                render::render_main(terminal, render::BufferType::None, todo_items)?
             },
             State::EnteringCommand => {
                 render::render_main(
                     terminal,
-                    render::BufferType::EnteringCommand(&user_input_buffer),
+                    render::BufferType::EnteringCommand(user_input_buffer),
                     todo_items)?
             },
             State::AddingTodo => {
-                render::render_adding(terminal, &user_input_buffer, todo_items)?
+                render::render_adding(terminal, user_input_buffer, todo_items)?
             },
             State::AddingTodoDate(state) => {
                 use AddState::*;
                 match state {
                     EnteringName => render::render_adding_date(
-                        &mut terminal,
-                        &*(user_input_buffer.to_owned() + "█"),
+                        terminal,
+                        &(user_input_buffer.to_owned() + "█"),
                         "",
                         "",
-                        &todo_items,
+                        todo_items,
                         &DateState::Year,
                     )?,
                     EnteringDate(state) => render::render_adding_date(
-                        &mut terminal,
-                        &storage_buff[..],
-                        &*(user_input_buffer.to_owned() + "█"),
+                        terminal,
+                        storage_buff,
+                        &(user_input_buffer.to_owned() + "█"),
                         date_storage_buff,
-                        &todo_items,
+                        todo_items,
                         &state,
                     )?,
                 }
             }
             State::CompletingTodo => render::render_main(
-                &mut terminal,
-                render::BufferType::CompletingTask(&user_input_buffer),
-                &todo_items,
+                terminal,
+                render::BufferType::CompletingTask(user_input_buffer),
+                todo_items,
             )?,
             State::UncompletingTodo => render::render_main(
-                &mut terminal,
-                render::BufferType::UncompletingTask(&user_input_buffer),
-                &todo_items,
+                terminal,
+                render::BufferType::UncompletingTask(user_input_buffer),
+                todo_items,
+            )?,
+            State::SettingPriority => render::render_main(
+                terminal,
+                render::BufferType::SettingPriority(user_input_buffer),
+                todo_items,
+            )?,
+            State::SettingRecurrence => render::render_main(
+                terminal,
+                render::BufferType::SettingRecurrence(user_input_buffer),
+                todo_items,
+            )?,
+            State::AddingSubtask => render::render_main(
+                terminal,
+                render::BufferType::AddingSubtask(user_input_buffer),
+                todo_items,
+            )?,
+            State::EnteringItemId(prompt) => render::render_main(
+                terminal,
+                render::BufferType::EnteringItemId(prompt.label(), user_input_buffer),
+                todo_items,
+            )?,
+            State::Filtering => render::render_main(
+                terminal,
+                render::BufferType::Filtering(user_input_buffer),
+                todo_items,
+            )?,
+            State::ViewingArchive(shelf) => render::render_main(
+                terminal,
+                render::BufferType::Archive(&shelf.to_string()),
+                todo_items,
+            )?,
+            State::SearchingArchive(shelf) => render::render_main(
+                terminal,
+                render::BufferType::SearchingArchive(&shelf.to_string(), user_input_buffer),
+                todo_items,
+            )?,
+            State::RestoringArchived(_) => render::render_main(
+                terminal,
+                render::BufferType::RestoringArchived(user_input_buffer),
+                todo_items,
+            )?,
+            State::ConfirmingDelete(ref item_ids) => {
+                let question = match item_ids.as_slice() {
+                    [item_id] => format!("Delete item {item_id}? (y/n)"),
+                    item_ids => format!("Delete {} items? (y/n)", item_ids.len()),
+                };
+                render::render_main(terminal, render::BufferType::Confirming(&question), todo_items)?
+            }
+            State::ConfirmingReload(_) => render::render_main(
+                terminal,
+                render::BufferType::Confirming(
+                    "Another program changed this list. r: reload it (u undoes that), o: overwrite it with yours",
+                ),
+                todo_items,
+            )?,
+            State::PickingList => {
+                render::render_main(terminal, render::BufferType::ListPicker, todo_items)?
+            }
+            State::ViewingKeys => render::render_main(terminal, render::BufferType::Keys, todo_items)?,
+            State::NamingList => render::render_main(
+                terminal,
+                render::BufferType::NamingList(user_input_buffer),
+                todo_items,
             )?,
-            State::Error => **current_state = State::Viewing,
             State::Quitting => {
                 return Ok(());
             }
@@ -289,103 +1062,192 @@ pub mod tui_handler {
         return Ok(());
     }
 
-    pub fn generate_todo(todo: &TodoList) -> render::TodoItems {
-        let mut indexes = String::from("\n");
-        let mut todos = String::from("Todo:\n");
-        let mut completions = String::from("\n");
+    pub fn generate_todo(todo: &TodoList, filter: &Filter) -> render::TodoItems {
+        let mut rows = match filter.is_empty() {
+            true => ItemRows::new("Todo".to_owned()),
+            false => ItemRows::new(format!("Todo ({filter})")),
+        };
 
-        let time_now = chrono::offset::Local::now();
-        let mut timer: [i64; 3] = [0, 0, 0];
+        sort_by_priority(&todo.todo_items, filter)
+            .into_iter()
+            .for_each(|item| rows.push_item(item, 0));
 
-        todo.todo_items
-            .iter()
-            .enumerate()
-            .for_each(|(index, item)| {
-                //todo items
-                indexes.push_str(&*format!(" {index}\n"));
-                todos.push_str(&*format!("{item_name}", item_name = item.title));
-                completions.push_str(&*format!("[{completed}]  \n", completed = COMPLETED_ITEM[0]));
-
-                if let Some(due) = item.due_date {
-                    let due_duration = due
-                        .signed_duration_since(time_now.naive_local())
-                        .num_seconds();
-                    timer = [
-                        (due_duration / 60) % 60,      //mins
-                        (due_duration / 60) / 60 % 24, //hrs
-                        (due_duration / 60) / 60 / 24, //days
-                    ];
-                    todos.push_str(&format!(
-                            " | Due: D:{d:0>2} H:{h:0>2} M:{m:0>2}",
-                            d = timer[2],
-                            h = timer[1],
-                            m = timer[0],
-                            ));
-
-                    //todo_str.push_str(&format!(" | Due: {:?}", due_duration));
-                }
-                todos.push_str("\n");
-            });
+        //completed items
+        rows.push_section("Completed Todos".to_owned());
+        sort_by_priority(&todo.completed_items, filter)
+            .into_iter()
+            .for_each(|item| rows.push_item(item, 0));
 
+        return rows.into_todo_items();
+    }
 
-        //completed items
-        indexes.push_str("\n\n\n");
-        todos.push_str("\n\nCompleted Todos:\n");
-        completions.push_str("\n\n\n");
-        
-        todo.completed_items
-            .iter()
-            .enumerate()
-            .for_each(|(index, item)| {
-                indexes.push_str(&*format!(" {index}\n"));
-                todos.push_str(&*format!("{item_name}\n", item_name = item.title));
-                completions.push_str(&*format!("[{completed}]  \n", completed = COMPLETED_ITEM[1]));
+    ///Lists the archived or deleted items whose title contains the search query
+    pub fn generate_archive(archive: &Archive, shelf: Shelf, query: &str) -> render::TodoItems {
+        let mut rows = match query.is_empty() {
+            true => ItemRows::new(shelf.to_string()),
+            false => ItemRows::new(format!("{shelf} (search: {query})")),
+        };
+
+        archive.search(shelf, query).for_each(|item| rows.push_item(item, 0));
+        return rows.into_todo_items();
+    }
+
+    ///One row per named list, the id of a row is its index in lists
+    pub fn generate_lists(lists: &[String], current_list: &str) -> render::TodoItems {
+        let mut section = render::ItemSection::new("Lists".to_owned());
+        for (index, name) in lists.iter().enumerate() {
+            let line = match name == current_list {
+                true => format!("  {name} (open)"),
+                false => format!("  {name}"),
+            };
+            section.rows.push(render::ItemRow { id: index, line: Spans::from(line) });
+        }
+        return render::TodoItems::new(vec![section]);
+    }
+
+    ///One row per action with the keys bound to it, unbound actions are listed too
+    pub fn generate_keys(keys: &KeyMap) -> render::TodoItems {
+        let mut section = render::ItemSection::new("Keys".to_owned());
+        for (index, (action, bound)) in keys.bindings().into_iter().enumerate() {
+            let bound = match bound.is_empty() {
+                true => "(unbound)".to_owned(),
+                false => bound.join(", "),
+            };
+            let line = format!("  {bound:<16} {:<16} {}", action.name(), action.description());
+            section.rows.push(render::ItemRow { id: index, line: Spans::from(line) });
+        }
+        return render::TodoItems::new(vec![section]);
+    }
+
+    ///Builds the list section by section, each item line starts with its id and checkbox
+    struct ItemRows {
+        sections: Vec<render::ItemSection>,
+        time_now: NaiveDateTime,
+    }
+    impl ItemRows {
+        fn new(title: String) -> ItemRows {
+            ItemRows {
+                sections: vec![render::ItemSection::new(title)],
+                time_now: chrono::offset::Local::now().naive_local(),
+            }
+        }
+        fn push_section(&mut self, title: String) {
+            self.sections.push(render::ItemSection::new(title));
+        }
+        ///Pushes the item and, unless it is collapsed, its subtasks indented below it
+        fn push_item(&mut self, item: &TodoItem, depth: usize) {
+            let completed = COMPLETED_ITEM[item.completed as usize];
+            let overdue = !item.completed && item.due_date.is_some_and(|due| due < self.time_now);
+            let suffix = match (item.completed, overdue) {
+                (true, _) => Span::raw(format_completed(item)),
+                (false, true) => Span::styled(format_due(item, self.time_now), render::overdue_style()),
+                (false, false) => Span::raw(format_due(item, self.time_now)),
+            };
+            let mut line = item_spans(item, depth, suffix);
+            line.0.insert(0, Span::raw(format!("{id:>4} [{completed}] ", id = item.id)));
+            let section = self.sections.last_mut().unwrap();
+            section.rows.push(render::ItemRow { id: item.id, line });
+
+            if !item.collapsed {
+                sort_by_priority(&item.children, &Filter::default())
+                    .into_iter()
+                    .for_each(|child| self.push_item(child, depth + 1));
+            }
+        }
+        fn into_todo_items(self) -> render::TodoItems {
+            return render::TodoItems::new(self.sections);
+        }
+    }
+
+    ///The title colored by priority (or in the completed style), followed by project and tag chips
+    ///and whatever is in suffix
+    fn item_spans(item: &TodoItem, depth: usize, suffix: Span<'static>) -> Spans<'static> {
+        let mut prefix = "  ".repeat(depth);
+        match (item.children.is_empty(), item.collapsed) {
+            (true, _) => {}
+            (false, true) => prefix.push_str("▸ "),
+            (false, false) => prefix.push_str("▾ "),
+        }
+
+        let mut spans = vec![
+            Span::raw(prefix),
+            Span::styled(format_title(item), match item.completed {
+                true => render::completed_style(),
+                false => render::priority_style(item.priority),
+            }),
+        ];
+        if let Some((done, total)) = item.progress() {
+            spans.push(Span::raw(format!(" {done}/{total}")));
+        }
+        if let Some(recurrence) = &item.recurrence {
+            spans.push(Span::raw(format!(" ↻ {recurrence}")));
+        }
+        if let Some(project) = &item.project {
+            spans.push(Span::raw(" "));
+            spans.push(Span::styled(format!("+{project}"), render::project_style()));
+        }
+        item.tags.iter().for_each(|tag| {
+            spans.push(Span::raw(" "));
+            spans.push(Span::styled(format!("@{tag}"), render::tag_style()));
         });
-        
-        return render::TodoItems::new(indexes, todos, completions);
+        spans.push(suffix);
+        return Spans::from(spans);
+    }
+
+    ///Only keeps items matching the filter, in the order they are shown in
+    fn sort_by_priority<'a>(items: &'a [TodoItem], filter: &Filter) -> Vec<&'a TodoItem> {
+        let mut sorted: Vec<&TodoItem> = items.iter().filter(|item| filter.matches(item)).collect();
+        sorted.sort_by(|a, b| compare_items(a, b));
+        return sorted;
+    }
+
+    fn format_due(item: &TodoItem, time_now: NaiveDateTime) -> String {
+        let due = match item.due_date {
+            Some(r) => r,
+            None => return String::new(),
+        };
+        let due_duration = due.signed_duration_since(time_now).num_seconds();
+        let timer = [
+            (due_duration / 60) % 60,      //mins
+            (due_duration / 60) / 60 % 24, //hrs
+            (due_duration / 60) / 60 / 24, //days
+        ];
+        return format!(
+            " | Due: D:{d:0>2} H:{h:0>2} M:{m:0>2}",
+            d = timer[2],
+            h = timer[1],
+            m = timer[0],
+        );
+    }
+
+    fn format_title(item: &TodoItem) -> String {
+        return match item.priority.marker() {
+            Some(marker) => format!("({marker}) {title}", title = item.title),
+            None => item.title.clone(),
+        };
+    }
+
+    fn format_completed(item: &TodoItem) -> String {
+        return match item.completed_at {
+            Some(done) => format!(" | Done: {done}", done = done.format("%b %d %Y")),
+            None => String::new(),
+        };
     }
 
+    ///Shows the error in the command bar, errors the TUI can't recover from are passed on
     pub fn handle_errors(
-        e: io::Error,
+        e: TodoError,
         terminal: &mut Terminal<CrosstermBackend<Stdout>>,
         todo_items: &render::TodoItems,
-    ) -> ResultIo<()> {
-        use ErrorKind::*;
-        match e.kind() {
-            InvalidInput => {
-                render::render_main(
-                    terminal,
-                    render::BufferType::Error("Invalid Input"),
-                    &todo_items,
-                )?;
-                return Ok(());
-            }
-            InvalidData => {
-                render::render_main(
-                    terminal,
-                    render::BufferType::Error("Invalid or Empty Data"),
-                    todo_items,
-                )?;
-                return Ok(());
-            }
-            Unsupported => {
-                render::render_main(
-                    terminal,
-                    render::BufferType::Error("Invalid Date"),
-                    todo_items,
-                )?;
-                return Ok(());
-            }
-            NotFound => {
-                render::render_main(
-                    terminal,
-                    render::BufferType::Error("Invalid Command"),
-                    todo_items,
-                    )?;
-                return Ok(());
-
-            }
-            _ => return Err(e),
+    ) -> TodoResult<()> {
+        if e.is_fatal() {
+            return Err(e);
         }
+        render::render_main(
+            terminal,
+            render::BufferType::Error(&e.to_string()),
+            todo_items,
+        )?;
+        return Ok(());
     }
 }
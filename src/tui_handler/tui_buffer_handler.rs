@@ -1,4 +1,5 @@
-use crate::todo_backend::todo::TodoList;
+use crate::todo_backend::todo::{Filter, Priority, Recurrence, Shelf, TodoError, TodoList, TodoResult};
+use crate::data_handler::data_handler::check_list_name;
 use crate::parsing_handler::{
     parse,
     handle_command,
@@ -6,72 +7,113 @@ use crate::parsing_handler::{
 
 use super::{
     tui_handler::{
-        generate_todo,
+        generate_archive,
         AddState,
         BufferAction,
         DateState,
-        State
+        State,
+        TuiData,
     },
-    tui_rendering_handler::TodoItems, 
 };
-use std::io::{self, ErrorKind};
 
 pub fn submit_buffer(
     current_state_data: &State,
     output_buffer: &str,
-    date_storage_buff: &String,
+    date_storage_buff: &str,
     todo: &mut TodoList,
-) -> io::Result<()> {
+) -> TodoResult<()> {
     match *current_state_data {
+        //a due date can be given inline, ex: "Write report due:next fri 5pm"
         State::AddingTodo => {
-            todo.add_item(output_buffer)?;
+            match split_due(output_buffer) {
+                (title, _) if title.trim().is_empty() => return Err(TodoError::parse(output_buffer, 0, "a title")),
+                (title, Some(date)) => todo.add_item_with_date(title, date)?,
+                (_, None) => todo.add_item(output_buffer)?,
+            };
         },
         State::AddingTodoDate(state) => {
             match state {
-                AddState::EnteringName => todo.add_item(&output_buffer)?,
+                AddState::EnteringName => todo.add_item(output_buffer)?,
                 AddState::EnteringDate(_) => {
                     // todo.add_item(&*format!("'{date_storage_buff}'"))?;
-                    todo.add_item_with_date(&output_buffer, &*date_storage_buff)?
+                    todo.add_item_with_date(output_buffer, date_storage_buff)?
                 }
             };
         }
-        _ => return Err(ErrorKind::InvalidInput.into())
+        _ => return Err(TodoError::invalid_input(output_buffer, "Nothing is being added"))
 
     }
     return Ok(());
 }
 
+///Splits "Write report due:next fri 5pm" into the title and the date. Only a word starting with due:
+///counts, so "Check overdue:items" is all title
+fn split_due(input: &str) -> (&str, Option<&str>) {
+    let due_at = input
+        .match_indices("due:")
+        .map(|(index, _)| index)
+        .find(|index| *index == 0 || input[..*index].ends_with(char::is_whitespace));
+    return match due_at {
+        Some(index) => (input[..index].trim(), Some(&input[index + "due:".len()..])),
+        None => (input, None),
+    };
+}
+
 pub fn manipulate_buffer(
     current_state: &mut State,
     action: BufferAction,
     user_input_buffer: &mut String,
     name_storage_buff: &mut String,
     date_storage_buff: &mut String,
-    todo: &mut TodoList,
-    todo_items: &mut TodoItems,
-) -> io::Result<()> {
+    data: &mut TuiData,
+) -> TodoResult<()> {
     match action {
         BufferAction::Input(input) => user_input_buffer.push(input),
         BufferAction::Backspace => {
             user_input_buffer.pop();
         }
+        BufferAction::ExitBuffer if current_state.shelf().is_some() => {
+            *current_state = State::ViewingArchive(current_state.shelf().unwrap());
+            data.refresh(current_state);
+            *user_input_buffer = String::new();
+        }
+        BufferAction::ExitBuffer if current_state.picking_list() => {
+            *current_state = State::PickingList;
+            *user_input_buffer = String::new();
+        }
         BufferAction::ExitBuffer => {
             *current_state = State::Viewing;
+            data.edit = None;
             *date_storage_buff = String::new();
             *user_input_buffer = String::new();
             *date_storage_buff = String::new();
         }
+        //only the edit form has more than one field
+        BufferAction::NextField | BufferAction::PreviousField => {
+            if let (State::EditingTodo(_), Some(form)) = (&current_state, &mut data.edit) {
+                let forward = matches!(action, BufferAction::NextField);
+                *user_input_buffer = form.switch_field(user_input_buffer, forward);
+            }
+        }
+        //anything the submit changes can be undone, even if it failed halfway
         BufferAction::SubmitBuffer => {
-            match_buffer_submit(
+            data.history.begin(data.todo, data.archive);
+            let result = match_buffer_submit(
                 &mut *current_state,
                 user_input_buffer,
                 name_storage_buff,
                 date_storage_buff,
-                todo,
-                todo_items,
-            )?;
+                data,
+            );
+            data.history.commit(data.todo, data.archive);
+            result?;
         }
     }
+
+    //the archive search filters as you type
+    if let State::SearchingArchive(shelf) = *current_state {
+        data.set_items(generate_archive(data.archive, shelf, user_input_buffer));
+    }
     return Ok(());
 }
 
@@ -80,33 +122,47 @@ fn match_buffer_submit(
     user_input_buffer: &mut String,
     name_storage_buff: &mut String,
     date_storage_buff: &mut String,
-    todo: &mut TodoList,
-    todo_items: &mut TodoItems,
-) -> io::Result<()> {     
+    data: &mut TuiData,
+) -> TodoResult<()> {     
     match *current_state {
+        //the filtered archive stays on screen until the archive is closed
+        State::SearchingArchive(shelf) => {
+            *current_state = State::ViewingArchive(shelf);
+            *user_input_buffer = String::new();
+        }
+        //an empty filter shows every item again
+        State::Filtering => {
+            data.filter = user_input_buffer.parse::<Filter>()?;
+            *current_state = State::Viewing;
+            data.refresh(current_state);
+            *user_input_buffer = String::new();
+        }
         State::AddingTodo => {
-            submit_buffer(&current_state, &user_input_buffer, date_storage_buff, todo)?;
-            *todo_items = generate_todo(todo);
+            submit_buffer(current_state, user_input_buffer, date_storage_buff, data.todo)?;
             *current_state = State::Viewing;
+            data.refresh(current_state);
             *user_input_buffer = String::new();
         }
         State::AddingTodoDate(AddState::EnteringName) => {
-            swap_buffers(&user_input_buffer, &mut *name_storage_buff)?;
+            swap_buffers(user_input_buffer, &mut *name_storage_buff)?;
             *user_input_buffer = String::new();
             *current_state = State::AddingTodoDate(AddState::EnteringDate(DateState::Year));
         }
         State::AddingTodoDate(AddState::EnteringDate(state)) => {
-            if let DateState::Time = state {
+            //anything other than a year in the first step is taken as the whole date
+            let full_date = matches!(state, DateState::Year)
+                && user_input_buffer.parse::<i32>().is_err();
+            if full_date || matches!(state, DateState::Time) {
                 *date_storage_buff += &*(user_input_buffer);
                 submit_buffer(
-                    &current_state,
+                    current_state,
                     &*name_storage_buff,
-                    &date_storage_buff,
-                    todo,
+                    date_storage_buff,
+                    data.todo,
                 )?;
 
                 *current_state = State::Viewing;
-                *todo_items = generate_todo(todo);
+                data.refresh(current_state);
                 *date_storage_buff = String::new();
                 *user_input_buffer = String::new();
                 *date_storage_buff = String::new();
@@ -116,11 +172,39 @@ fn match_buffer_submit(
                 *user_input_buffer = String::new();
             }
         }
+        //a list that doesn't exist yet is created when it is opened
+        State::NamingList => {
+            let name = user_input_buffer.trim().to_owned();
+            check_list_name(&name)?;
+            if !data.lists.contains(&name) {
+                data.lists.push(name.clone());
+            }
+            data.switch_list(current_state, &name);
+            *user_input_buffer = String::new();
+        }
+        //the changed fields are applied as one edit command
+        State::EditingTodo(_) => {
+            let mut form = match data.edit.take() {
+                Some(r) => r,
+                None => return Err(TodoError::invalid_input(user_input_buffer, "Nothing is being edited")),
+            };
+            form.switch_field(user_input_buffer, true);
+            if let Some(command) = form.to_command()? {
+                handle_command(parse(&command)?, data.todo, data.archive, &mut data.filter, data.history)?;
+            }
+            *current_state = State::Viewing;
+            data.refresh(current_state);
+            *user_input_buffer = String::new();
+        }
         //commands go here (will probably move this out at some point
         _ => {
-            submit_command(&mut *current_state, &user_input_buffer, todo)?;
-            *todo_items = generate_todo(todo);
+            submit_command(&mut *current_state, user_input_buffer, data)?;
+            data.refresh(current_state);
             *user_input_buffer = String::from("");
+            //":edit 3" opens the edit form
+            if let State::EditingTodo(item_id) = *current_state {
+                *user_input_buffer = data.start_edit(item_id)?;
+            }
         }
     }
     return Ok(());
@@ -129,33 +213,122 @@ fn match_buffer_submit(
 pub fn submit_command(
     current_state: &mut State,
     output_buffer: &str,
-    todo: &mut TodoList,
-) -> io::Result<()> {
+    data: &mut TuiData,
+) -> TodoResult<()> {
     if let State::EnteringCommand = current_state {
         let parsed = parse(output_buffer)?; 
-        *current_state = handle_command(parsed)?;
+        *current_state = handle_command(parsed, data.todo, data.archive, &mut data.filter, data.history)?;
         return Ok(());
     }
 
-    let output_buffer = match output_buffer.parse::<usize>() {
-        Ok(r) => r,
-        Err(_) => return Err(ErrorKind::InvalidInput.into()),
-    };
+    let (todo, archive) = (&mut *data.todo, &mut *data.archive);
+
+    //expects the id of the parent followed by the name of the subtask, ex: "3 Write tests"
+    if let State::AddingSubtask = current_state {
+        let (parent_id, title) = split_id(output_buffer, "the name of the subtask")?;
+        todo.add_subtask(parent_id, title)?;
+        *current_state = State::Viewing;
+        return Ok(());
+    }
+
+    //expects the id followed by the rule, ex: "3 weekly mon fri", "none" stops the repeating
+    if let State::SettingRecurrence = current_state {
+        let (item_id, rule) = split_id(output_buffer, "a rule")?;
+        let recurrence = match rule {
+            "none" => None,
+            rule => Some(rule.parse::<Recurrence>()?),
+        };
+        todo.set_recurrence(item_id, recurrence)?;
+        *current_state = State::Viewing;
+        return Ok(());
+    }
+
+    //expects the id followed by the priority, ex: "3 high"
+    if let State::SettingPriority = current_state {
+        let (item_id, priority) = split_id(output_buffer, "a priority")?;
+        todo.set_priority(item_id, priority.parse::<Priority>()?)?;
+        *current_state = State::Viewing;
+        return Ok(());
+    }
+
+    let item_id = parse_id(output_buffer)?;
 
     match *current_state {
         State::CompletingTodo => {
-            todo.complete_item(output_buffer)?;
+            todo.complete_item(item_id, false)?;
+        }
+        State::EnteringItemId(prompt) => {
+            prompt.apply(todo, item_id)?;
         }
         State::UncompletingTodo => {
-            todo.uncomplete_item(output_buffer)?;
+            todo.uncomplete_item(item_id)?;
         } 
+        State::RestoringArchived(shelf) => {
+            match shelf {
+                Shelf::Archive => todo.restore_archived(item_id, archive)?,
+                Shelf::Trash => todo.restore_deleted(item_id, archive)?,
+            }
+            *current_state = State::ViewingArchive(shelf);
+            return Ok(());
+        }
         _ => {}
     }
 
     *current_state = State::Viewing;
     return Ok(());
 }
-pub fn swap_buffers(prev_buff: &str, storage_buff: &mut String) -> io::Result<()> {
+
+fn parse_id(output_buffer: &str) -> TodoResult<usize> {
+    let trimmed = output_buffer.trim_start();
+    let position = output_buffer.len() - trimmed.len();
+    return match trimmed.trim_end().parse::<usize>() {
+        Ok(r) => Ok(r),
+        Err(_) => Err(TodoError::parse(output_buffer, position, "an id")),
+    };
+}
+
+///Splits buffers of the form "<id> <rest>", rest_name describes what should follow the id
+fn split_id<'a>(output_buffer: &'a str, rest_name: &str) -> TodoResult<(usize, &'a str)> {
+    let trimmed = output_buffer.trim();
+    let (item_id, rest) = match trimmed.split_once(' ') {
+        Some(r) => r,
+        None => return Err(TodoError::parse(output_buffer, output_buffer.len(), rest_name)),
+    };
+    return Ok((parse_id(item_id)?, rest.trim()));
+}
+
+pub fn swap_buffers(prev_buff: &str, storage_buff: &mut String) -> TodoResult<()> {
     *storage_buff = prev_buff.to_string();
     return Ok(());
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{split_due, submit_buffer};
+    use crate::todo_backend::todo::TodoList;
+    use crate::tui_handler::tui_handler::State;
+
+    #[test]
+    fn due_word_splits_off_the_date() {
+        assert_eq!(split_due("Write report due:next fri 5pm"), ("Write report", Some("next fri 5pm")));
+        assert_eq!(split_due("due:tomorrow"), ("", Some("tomorrow")));
+    }
+
+    #[test]
+    fn due_inside_a_word_stays_in_the_title() {
+        assert_eq!(split_due("Check overdue:items"), ("Check overdue:items", None));
+        assert_eq!(split_due("Check overdue:items due:eod"), ("Check overdue:items", Some("eod")));
+    }
+
+    #[test]
+    fn a_due_date_alone_is_not_a_task() {
+        let mut todo = TodoList::new();
+        for input in ["due:tomorrow", "  due:tomorrow", ""] {
+            assert!(submit_buffer(&State::AddingTodo, input, "", &mut todo).is_err(), "{input}");
+        }
+        assert!(todo.todo_items.is_empty());
+        submit_buffer(&State::AddingTodo, "Water plants due:tomorrow", "", &mut todo).unwrap();
+        assert_eq!(todo.todo_items[0].title, "Water plants");
+        assert!(todo.todo_items[0].due_date.is_some());
+    }
+}
@@ -2,23 +2,22 @@ use std::sync::mpsc::Sender;
 
 use super::tui_handler::MAX_TICK_TIME;
 use super::tui_handler::*;
+use crate::config_handler::config_handler::{KeyAction, KeyMap};
+use crate::todo_backend::todo::{Priority, Shelf, TodoResult};
 use crossterm::event as CEvent;
 use std::sync::{Arc, Mutex};
-use std::{
-    io,
-    time::{Duration, Instant},
-};
+use std::time::{Duration, Instant};
 
 pub fn capture_input(
     sx: &Sender<Event<CEvent::KeyEvent>>,
     current_tick_time: &mut Instant,
-) -> io::Result<()> {
+) -> crossterm::Result<()> {
     let event_timer = MAX_TICK_TIME
         .checked_sub(current_tick_time.elapsed())
         .unwrap_or_else(|| Duration::from_secs(0));
 
-    if CEvent::poll(event_timer).expect("Polling Doesn't Work") {
-        if let CEvent::Event::Key(key) = CEvent::read().expect("Reading Events Doesn't Work") {
+    if CEvent::poll(event_timer)? {
+        if let CEvent::Event::Key(key) = CEvent::read()? {
             sx.send(Event::Input(key)).expect("Sending Events");
         }
     }
@@ -36,24 +35,74 @@ pub fn capture_input(
 pub fn handle_input(
     input: CEvent::KeyEvent,
     current_state: &Arc<Mutex<State>>,
-) -> io::Result<UserAction> {
+    keys: &KeyMap,
+) -> TodoResult<UserAction> {
     use crossterm::event::KeyCode;
     let current_state_data = current_state.lock().unwrap();
+    let action = keys.action(input);
 
-    //handles user actions in normal mode
+    //moving the selection works the same in the list, the archive, the list picker and the keys view
+    if let State::Viewing | State::ViewingArchive(_) | State::PickingList | State::ViewingKeys = *current_state_data {
+        let movement = match action {
+            Some(KeyAction::Up) => Some(Movement::Up),
+            Some(KeyAction::Down) => Some(Movement::Down),
+            Some(KeyAction::PageUp) => Some(Movement::PageUp),
+            Some(KeyAction::PageDown) => Some(Movement::PageDown),
+            Some(KeyAction::Top) => Some(Movement::Top),
+            Some(KeyAction::Bottom) => Some(Movement::Bottom),
+            Some(KeyAction::NextSection) => Some(Movement::NextSection),
+            _ => None,
+        };
+        if let Some(movement) = movement {
+            return Ok(UserAction::MoveSelection(movement));
+        }
+    }
+
+    //handles user actions in normal mode, the keys come from the config
     if let State::Viewing = *current_state_data {
-        let key = match input.code {
-            KeyCode::Char(input) => input,
-            _ => return Ok(UserAction::None),
+        return match action {
+            Some(action) => Ok(user_action(action)),
+            None => Ok(UserAction::None),
+        };
+    }
+
+    //handles user actions while browsing the archive
+    if let State::ViewingArchive(_) = *current_state_data {
+        return match input.code {
+            KeyCode::Char('/') => Ok(UserAction::SearchArchive),
+            KeyCode::Char('r') => Ok(UserAction::RestoreArchived),
+            KeyCode::Char('q') | KeyCode::Esc => Ok(UserAction::CloseArchive),
+            _ => Ok(UserAction::None),
+        };
+    }
+
+    if let State::ViewingKeys = *current_state_data {
+        return match input.code {
+            KeyCode::Char('q') | KeyCode::Esc => Ok(UserAction::CloseView),
+            _ => Ok(UserAction::None),
         };
+    }
 
-        return match key {
-            ':' => Ok(UserAction::Command),
-            'q' => Ok(UserAction::Quit),
-            'n' => Ok(UserAction::AddTodo),
-            'd' => Ok(UserAction::AddTodoDate),
-            'c' => Ok(UserAction::CompeleteTodo),
-            'u' => Ok(UserAction::UncompleteTodo),
+    //handles user actions in the list picker
+    if let State::PickingList = *current_state_data {
+        return match input.code {
+            KeyCode::Enter => Ok(UserAction::OpenSelectedList),
+            KeyCode::Char('n') => Ok(UserAction::NewList),
+            KeyCode::Char('q') | KeyCode::Esc => Ok(UserAction::CloseView),
+            _ => Ok(UserAction::None),
+        };
+    }
+
+    //anything but y cancels
+    if let State::ConfirmingDelete(_) = *current_state_data {
+        return Ok(UserAction::ConfirmDelete(input.code == KeyCode::Char('y')));
+    }
+
+    //one of the two has to be picked, the list can't be saved until then
+    if let State::ConfirmingReload(_) = *current_state_data {
+        return match input.code {
+            KeyCode::Char('r') => Ok(UserAction::ConfirmReload(true)),
+            KeyCode::Char('o') => Ok(UserAction::ConfirmReload(false)),
             _ => Ok(UserAction::None),
         };
     }
@@ -65,6 +114,42 @@ pub fn handle_input(
         KeyCode::Backspace => return Ok(UserAction::ManipulateBuffer(Backspace)),
         KeyCode::Enter => return Ok(UserAction::ManipulateBuffer(SubmitBuffer)),
         KeyCode::Esc => return Ok(UserAction::ManipulateBuffer(ExitBuffer)),
+        KeyCode::Tab => return Ok(UserAction::ManipulateBuffer(NextField)),
+        KeyCode::BackTab => return Ok(UserAction::ManipulateBuffer(PreviousField)),
         _ => return Ok(UserAction::None),
     };
 }
+
+fn user_action(action: KeyAction) -> UserAction {
+    return match action {
+        KeyAction::Command => UserAction::Command,
+        KeyAction::Quit => UserAction::Quit,
+        KeyAction::Add => UserAction::AddTodo,
+        KeyAction::AddDate => UserAction::AddTodoDate,
+        KeyAction::Complete => UserAction::CompeleteTodo,
+        KeyAction::CompleteAll => UserAction::PromptItem(ItemPrompt::CompleteWithSubtasks),
+        KeyAction::Uncomplete => UserAction::UncompleteTodo,
+        KeyAction::Priority => UserAction::SetPriority,
+        KeyAction::Repeat => UserAction::SetRecurrence,
+        KeyAction::Filter => UserAction::Filter,
+        KeyAction::Subtask => UserAction::AddSubtask,
+        KeyAction::Indent => UserAction::PromptItem(ItemPrompt::Indent),
+        KeyAction::Outdent => UserAction::PromptItem(ItemPrompt::Outdent),
+        KeyAction::Toggle => UserAction::PromptItem(ItemPrompt::ToggleCollapse),
+        KeyAction::Archive => UserAction::ShowArchive(Shelf::Archive),
+        KeyAction::Trash => UserAction::ShowArchive(Shelf::Trash),
+        KeyAction::Undo => UserAction::Undo,
+        KeyAction::Redo => UserAction::Redo,
+        KeyAction::Delete => UserAction::DeleteSelected,
+        KeyAction::Edit => UserAction::EditSelected,
+        KeyAction::Lists => UserAction::PickList,
+        KeyAction::Keys => UserAction::ShowKeys,
+        KeyAction::PriorityNone => UserAction::PrioritizeSelected(Priority::None),
+        KeyAction::PriorityLow => UserAction::PrioritizeSelected(Priority::Low),
+        KeyAction::PriorityMedium => UserAction::PrioritizeSelected(Priority::Medium),
+        KeyAction::PriorityHigh => UserAction::PrioritizeSelected(Priority::High),
+        KeyAction::PriorityUrgent => UserAction::PrioritizeSelected(Priority::Urgent),
+        //movement is handled before the other actions
+        _ => UserAction::None,
+    };
+}
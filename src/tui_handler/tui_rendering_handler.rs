@@ -1,88 +1,202 @@
-use std::io::{self, Stdout};
+use std::{cell::Cell, io::{self, Stdout}, sync::OnceLock};
 use tui::{
     backend::CrosstermBackend,
     layout::{self, Rect},
-    style::{Color, Modifier, Style},
-    widgets::{self, Paragraph}, Terminal,
+    style::Style,
+    text::{Span, Spans},
+    widgets::{self, List, ListItem, ListState}, Frame, Terminal,
 };
 use super::tui_handler::DateState;
+use crate::config_handler::config_handler::Theme;
+use crate::todo_backend::todo::Priority;
 
 const TODO_SIZE: u16 = 30;
+//share of the list the completed items get
+const COMPLETED_SIZE: u16 = 35;
 
 pub enum BufferType<'a> {
     None,
     EnteringCommand(&'a str),
-    AddingTask(&'a str),
     CompletingTask(&'a str),
     UncompletingTask(&'a str),
+    SettingPriority(&'a str),
+    SettingRecurrence(&'a str),
+    Filtering(&'a str),
+    AddingSubtask(&'a str),
+    EnteringItemId(&'a str, &'a str),
+    Archive(&'a str),
+    SearchingArchive(&'a str, &'a str),
+    RestoringArchived(&'a str),
+    Confirming(&'a str),
+    ListPicker,
+    Keys,
+    NamingList(&'a str),
     Error(&'a str),
 }
 
-#[derive(Clone)]
+pub struct ItemRow {
+    pub id: usize,
+    pub line: Spans<'static>,
+}
+
+///A titled part of the list that scrolls on its own, ex: the todo items or the completed items
+pub struct ItemSection {
+    pub title: String,
+    pub rows: Vec<ItemRow>,
+    //first visible row, moved while rendering so the selected row stays on screen
+    offset: Cell<usize>,
+}
+impl ItemSection {
+    pub fn new(title: String) -> ItemSection {
+        ItemSection {title, rows: Vec::new(), offset: Cell::new(0)}
+    }
+    pub fn offset(&self) -> usize {
+        return self.offset.get();
+    }
+    ///Scrolls as little as possible to show the selected row within height rows
+    fn scroll(&self, selected: Option<usize>, height: usize) -> usize {
+        //a terminal too small to show any rows still keeps the selected row at the top
+        let height = height.max(1);
+        let mut offset = self.offset.get();
+        if let Some(selected) = selected {
+            if selected < offset {
+                offset = selected;
+            } else if selected >= offset + height {
+                offset = selected + 1 - height;
+            }
+        }
+        offset = offset.min(self.rows.len().saturating_sub(height));
+        self.offset.set(offset);
+        return offset;
+    }
+}
+
 pub struct TodoItems {
-    pub indexes: String,
-    pub todo_content: String,
-    pub check_boxes: String,
+    pub sections: Vec<ItemSection>,
+    //section and row of the highlighted item
+    pub selected: Option<(usize, usize)>,
 }
 impl TodoItems {
-    pub fn new(indexes: String, todo_content: String, check_boxes: String) -> TodoItems {
-        TodoItems {indexes, todo_content, check_boxes} 
+    pub fn new(sections: Vec<ItemSection>) -> TodoItems {
+        TodoItems {sections, selected: None}
+    }
+    pub fn selected_id(&self) -> Option<usize> {
+        let (section, row) = self.selected?;
+        return self.sections.get(section)?.rows.get(row).map(|row| row.id);
+    }
+    pub fn position_of(&self, item_id: usize) -> Option<(usize, usize)> {
+        return self.selectable().into_iter().find(|(section, row)| {
+            self.sections[*section].rows[*row].id == item_id
+        });
     }
+    ///Every row in the order they are shown
+    pub fn selectable(&self) -> Vec<(usize, usize)> {
+        return self.sections
+            .iter()
+            .enumerate()
+            .flat_map(|(index, section)| (0..section.rows.len()).map(move |row| (index, row)))
+            .collect();
+    }
+    ///Keeps the scroll positions of the sections that are still there
+    pub fn keep_scroll(&self, previous: &TodoItems) {
+        self.sections
+            .iter()
+            .zip(previous.sections.iter())
+            .for_each(|(section, previous)| section.offset.set(previous.offset()));
+    }
+}
+
+//set once when the TUI starts, the dark theme until then
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+pub fn set_theme(theme: Theme) {
+    let _ = THEME.set(theme);
+}
+
+fn theme() -> &'static Theme {
+    return THEME.get_or_init(Theme::default);
+}
+
+pub fn priority_style(priority: Priority) -> Style {
+    let index = match priority {
+        Priority::None => 0,
+        Priority::Low => 1,
+        Priority::Medium => 2,
+        Priority::High => 3,
+        Priority::Urgent => 4,
+    };
+    return theme().priorities[index];
+}
+
+pub fn project_style() -> Style {
+    return theme().project;
+}
+
+pub fn tag_style() -> Style {
+    return theme().tag;
+}
+
+pub fn completed_style() -> Style {
+    return theme().completed;
+}
+
+pub fn overdue_style() -> Style {
+    return theme().overdue;
 }
 macro_rules! generate_page_section {
     () => {
         widgets::Paragraph::new("")
-            .style(Style::default().fg(Color::LightCyan))
+            .style(theme().list)
             .alignment(layout::Alignment::Center)
             .block(
                 widgets::Block::default()
                 .borders(widgets::Borders::NONE)
-                .style(Style::default().fg(Color::White))
+                .style(theme().border)
                 .border_type(widgets::BorderType::Plain),
                 )
     };
     ($text: literal) => {
         widgets::Paragraph::new($text)
-            .style(Style::default().fg(Color::LightCyan))
+            .style(theme().header)
             .alignment(layout::Alignment::Center)
             .block(
                 widgets::Block::default()
                 .borders(widgets::Borders::ALL)
-                .style(Style::default().fg(Color::White))
+                .style(theme().border)
                 .border_type(widgets::BorderType::Plain),
                 )
     };
     ($text: ident) => {
         widgets::Paragraph::new($text)
-            .style(Style::default().fg(Color::LightCyan))
+            .style(theme().input)
             .alignment(layout::Alignment::Center)
             .block(
                 widgets::Block::default()
                 .borders(widgets::Borders::ALL)
-                .style(Style::default().fg(Color::White))
+                .style(theme().border)
                 .border_type(widgets::BorderType::Plain),
                 )
     };
-    ($text: ident, $text_color: ident) => {
+    ($text: ident, $text_style: ident) => {
         widgets::Paragraph::new($text)
-            .style(Style::default().fg($text_color))
+            .style($text_style)
             .alignment(layout::Alignment::Center)
             .block(
                 widgets::Block::default()
                 .borders(widgets::Borders::ALL)
-                .style(Style::default().fg(Color::White))
+                .style(theme().border)
                 .border_type(widgets::BorderType::Plain),
                 )
     };
-    ($text: ident, $section_title: literal, $color: ident) => {
+    ($text: ident, $section_title: literal, $border_style: ident) => {
         widgets::Paragraph::new($text)
-            .style(Style::default().fg(Color::LightCyan))
+            .style(theme().list)
             .alignment(layout::Alignment::Center)
             .block(
                 widgets::Block::default()
                 .borders(widgets::Borders::ALL)
                 .title($section_title)
-                .style(Style::default().fg($color))
+                .style($border_style)
                 .border_type(widgets::BorderType::Plain),
                 )
     }
@@ -95,10 +209,21 @@ pub fn render_main(
 ) -> io::Result<()> {
     let command_contents = match buffer {
         BufferType::None => "Command Mode".to_owned(),
-        BufferType::AddingTask(b) => "Adding: ".to_owned() + b,
         BufferType::CompletingTask(b) => "CompletingTask: ".to_owned() + b,
         BufferType::UncompletingTask(b) => "UncompletingTask: ".to_owned() + b,
         BufferType::EnteringCommand(b) => "Command: ".to_owned() + b,
+        BufferType::SettingPriority(b) => "SetPriority (id priority): ".to_owned() + b,
+        BufferType::SettingRecurrence(b) => "SetRecurrence (id rule): ".to_owned() + b,
+        BufferType::AddingSubtask(b) => "AddSubtask (parent id, name): ".to_owned() + b,
+        BufferType::EnteringItemId(label, b) => format!("{label}: {b}"),
+        BufferType::Filtering(b) => "Filter (+project @tag): ".to_owned() + b,
+        BufferType::Archive(shelf) => format!("{shelf} | /: Search, r: Restore, q: Close"),
+        BufferType::SearchingArchive(shelf, b) => format!("Search {shelf}: {b}"),
+        BufferType::RestoringArchived(b) => "Restoring: ".to_owned() + b,
+        BufferType::Confirming(question) => question.to_owned(),
+        BufferType::ListPicker => "Lists | Enter: Open, n: New list, q: Close".to_owned(),
+        BufferType::Keys => "Keys | q: Close".to_owned(),
+        BufferType::NamingList(b) => "New list: ".to_owned() + b,
         BufferType::Error(e) => "Error: ".to_owned() + e,
     };
 
@@ -118,16 +243,15 @@ pub fn render_main(
                     )
                 .split(size);
 
-            let (content, todo_content, indexes, todos, completions) =   
-                generate_content(&chunks, todo_items).unwrap();
+            let content = generate_content(&chunks);
 
             let header = generate_page_section!("TODO LIST");
             let empty_left = generate_page_section!();
             let empty_right = generate_page_section!();
             let command_buffer = match buffer{
                 BufferType::Error(_) =>{
-                    let color = Color::Red;
-                    generate_page_section!(command_contents, color) 
+                    let style = theme().error;
+                    generate_page_section!(command_contents, style)
                 },
                 _ => generate_page_section!(command_contents),
             };
@@ -135,9 +259,7 @@ pub fn render_main(
             rec.render_widget(header, chunks[0]);
             rec.render_widget(empty_left, content[0]);
 
-            rec.render_widget(indexes, todo_content[0]);
-            rec.render_widget(todos, todo_content[1]);
-            rec.render_widget(completions, todo_content[2]);
+            render_sections(rec, content[1], todo_items);
 
             rec.render_widget(empty_right, content[2]);
             rec.render_widget(command_buffer, chunks[2]);
@@ -172,19 +294,16 @@ pub fn render_adding(
             
             let header = generate_page_section!("TODO LIST");
             let empty_left = generate_page_section!();
-            let color = Color::LightGreen;
-            let new_todo = generate_page_section!(todo_string, "AddingTask", color);
+            let style = theme().input;
+            let new_todo = generate_page_section!(todo_string, "AddingTask", style);
             let command_buffer = generate_page_section!("AddingTask");
 
-            let (content, todo_content, indexes, todos, completions) =   
-                generate_content(&chunks, todo_items).unwrap();
+            let content = generate_content(&chunks);
 
             rec.render_widget(header, chunks[0]);
             rec.render_widget(empty_left, content[0]);
             
-            rec.render_widget(indexes, todo_content[0]);
-            rec.render_widget(todos, todo_content[1]);
-            rec.render_widget(completions, todo_content[2]);
+            render_sections(rec, content[1], todo_items);
 
             rec.render_widget(new_todo, content[2]);
             rec.render_widget(command_buffer, chunks[2]);
@@ -193,6 +312,48 @@ pub fn render_adding(
     Ok(())
 }
 
+///The edit form goes where new tasks are entered, the field being typed in is marked with >
+pub fn render_editing(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    form_text: &str,
+    todo_items: &TodoItems,
+) -> io::Result<()> {
+    terminal
+        .draw(|rec| {
+            let size = rec.size();
+            let chunks = layout::Layout::default()
+                .direction(layout::Direction::Vertical)
+                .margin(2)
+                .constraints(
+                    [
+                    layout::Constraint::Length(3), //Adding
+                    layout::Constraint::Min(2),    //Content
+                    layout::Constraint::Length(3), //Footer
+                    ]
+                    .as_ref(),
+                    )
+                .split(size);
+
+            let header = generate_page_section!("TODO LIST");
+            let empty_left = generate_page_section!();
+            let style = theme().input;
+            let form = generate_page_section!(form_text, "EditingTask", style);
+            let command_buffer = generate_page_section!("Tab/Shift-Tab: Switch Field, Enter: Save, Esc: Cancel");
+
+            let content = generate_content(&chunks);
+
+            rec.render_widget(header, chunks[0]);
+            rec.render_widget(empty_left, content[0]);
+
+            render_sections(rec, content[1], todo_items);
+
+            rec.render_widget(form, content[2]);
+            rec.render_widget(command_buffer, chunks[2]);
+        })
+        .expect("Drawing TUI");
+    Ok(())
+}
+
 pub fn render_adding_date(
     terminal: &mut Terminal<CrosstermBackend<Stdout>>,
     name_buffer: &str,
@@ -203,7 +364,7 @@ pub fn render_adding_date(
 ) -> io::Result<()> {
     let mut todo_string = format!(" Task Name: {name_buffer}\n ");
     match date_state {
-        DateState::Year => todo_string += &*("Enter Year: ".to_owned() + date_buffer),
+        DateState::Year => todo_string += &*("Enter Year (or a full date, ex: tomorrow 5pm): ".to_owned() + date_buffer),
         DateState::Month => todo_string += &*("Enter Month: ".to_owned() + date_buffer),
         DateState::Day => todo_string += &*("Enter Day: ".to_owned() + date_buffer),
         DateState::Time => todo_string += &*("Enter Time: ".to_owned() + date_buffer),
@@ -228,12 +389,11 @@ pub fn render_adding_date(
 
             let header = generate_page_section!("TODO LIST");
             let empty_left = generate_page_section!();
-            let color = Color::LightGreen;
-            let new_todo = generate_page_section!(todo_string, "AddingTask", color);
+            let style = theme().input;
+            let new_todo = generate_page_section!(todo_string, "AddingTask", style);
             let command_buffer = generate_page_section!("AddingTask");
 
-            let (content, todo_content, indexes, todos, completions) =   
-                generate_content(&chunks, todo_items).unwrap();
+            let content = generate_content(&chunks);
             
            //header area
             rec.render_widget(header, chunks[0]);
@@ -242,9 +402,7 @@ pub fn render_adding_date(
             rec.render_widget(empty_left, content[0]);
             rec.render_widget(new_todo, content[2]);
             //todo content (in the middle)
-            rec.render_widget(indexes, todo_content[0]);
-            rec.render_widget(todos, todo_content[1]);
-            rec.render_widget(completions, todo_content[2]);
+            render_sections(rec, content[1], todo_items);
             
             //bottom area
             rec.render_widget(command_buffer, chunks[2]);
@@ -253,11 +411,8 @@ pub fn render_adding_date(
     Ok(())
 }
 
-fn generate_content<'a>(
-    chunks: &Vec<Rect>,
-    todo_items: &'a TodoItems) -> 
-io::Result<(Vec<Rect>, Vec<Rect>, Paragraph<'a>, Paragraph<'a>, Paragraph<'a>)> {
-    let content = layout::Layout::default()
+fn generate_content(chunks: &[Rect]) -> Vec<Rect> {
+    return layout::Layout::default()
         .direction(layout::Direction::Horizontal)
         .margin(0)
         .constraints(
@@ -269,49 +424,60 @@ io::Result<(Vec<Rect>, Vec<Rect>, Paragraph<'a>, Paragraph<'a>, Paragraph<'a>)>
             .as_ref(),
             )
         .split(chunks[1]);
+}
 
-    const MARGIN_SIZE: u16 = 10;
-    let todo_content = layout::Layout::default()
-        .direction(layout::Direction::Horizontal)
+///Stacks the sections on top of each other, the first one (the todo items) gets the most room.
+///Each section scrolls to keep the selection visible and says how many rows are out of view
+fn render_sections(rec: &mut Frame<CrosstermBackend<Stdout>>, area: Rect, todo_items: &TodoItems) {
+    let constraints: Vec<layout::Constraint> = (0..todo_items.sections.len())
+        .map(|index| match index {
+            0 => layout::Constraint::Min(4),
+            _ => layout::Constraint::Percentage(COMPLETED_SIZE),
+        })
+        .collect();
+    let areas = layout::Layout::default()
+        .direction(layout::Direction::Vertical)
         .margin(1)
-        .constraints(
-            [
-            layout::Constraint::Percentage(MARGIN_SIZE),
-            layout::Constraint::Percentage(100 - (MARGIN_SIZE * 2)),
-            layout::Constraint::Percentage(MARGIN_SIZE),
-            ]
-            .as_ref(),
-            )
-        .split(content[1]);
-    
-    let indexes = widgets::Paragraph::new(&*todo_items.indexes)
-        .style(Style::default().fg(Color::LightCyan))
-        .alignment(layout::Alignment::Left)
-        .block(
-            widgets::Block::default()
-            .borders(widgets::Borders::LEFT)
-            .style(Style::default().fg(Color::White))
-            .border_type(widgets::BorderType::Thick),
-            );
- 
-    let todos = widgets::Paragraph::new(&*todo_items.todo_content)
-    .style(Style::default().fg(Color::LightCyan))
-    .alignment(layout::Alignment::Left)
-    .block(
-        widgets::Block::default()
-        .borders(widgets::Borders::NONE)
-        .style(Style::default().fg(Color::White))
-        .border_type(widgets::BorderType::Plain),
-        );
-
-    let completions = widgets::Paragraph::new(&*todo_items.check_boxes)
-    .style(Style::default().fg(Color::LightCyan))
-    .alignment(layout::Alignment::Right)
-    .block(
-        widgets::Block::default()
-        .borders(widgets::Borders::RIGHT)
-        .style(Style::default().fg(Color::White))
-        .border_type(widgets::BorderType::Thick),
-        ); 
-    return Ok((content, todo_content, indexes, todos, completions));
+        .constraints(constraints)
+        .split(area);
+
+    for (index, (section, area)) in todo_items.sections.iter().zip(areas).enumerate() {
+        //the top border holds the title
+        let height = area.height.saturating_sub(1) as usize;
+        let selected = match todo_items.selected {
+            Some((selected_section, row)) if selected_section == index => Some(row),
+            _ => None,
+        };
+        let offset = section.scroll(selected, height);
+        let below = section.rows.len().saturating_sub(offset + height);
+
+        let mut title = section.title.clone();
+        if offset > 0 {
+            title += &format!(" ↑ {offset} more");
+        }
+        if below > 0 {
+            title += &format!(" ↓ {below} more");
+        }
+
+        let items: Vec<ListItem> = section.rows
+            .iter()
+            .skip(offset)
+            .take(height)
+            .map(|row| ListItem::new(row.line.clone()))
+            .collect();
+        let list = List::new(items)
+            .style(theme().list)
+            .highlight_style(theme().selected)
+            .block(
+                widgets::Block::default()
+                .borders(widgets::Borders::TOP | widgets::Borders::LEFT | widgets::Borders::RIGHT)
+                .title(Span::styled(title, theme().header))
+                .style(theme().border)
+                .border_type(widgets::BorderType::Thick),
+                );
+
+        let mut list_state = ListState::default();
+        list_state.select(selected.and_then(|row| row.checked_sub(offset)));
+        rec.render_stateful_widget(list, area, &mut list_state);
+    }
 }